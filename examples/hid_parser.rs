@@ -1,36 +1,45 @@
-use hidapi::{HidApi, HidResult};
+use hidapi::HidApi;
 
 fn main() {
     println!("Printing all available hid devices:");
 
     match HidApi::new() {
         Ok(api) => {
-            for device in api.device_list() {
-                if device.vendor_id() == concept2::consts::CONCEPT2_VENDOR_ID {
-                    println!("Found a device!");
-                    let csafe_cmd = concept2::csafe::CSAFEFrame::new(vec![
-                        concept2::concept2command::Concept2Command::ProprietaryCommand(vec![
-                            concept2::concept2command::Concept2ProprietaryCommand::GetWorkTime,
-                            concept2::concept2command::Concept2ProprietaryCommand::GetWorkDistance,
-                            concept2::concept2command::Concept2ProprietaryCommand::GetWorkoutType,
-                        ]),
-                        concept2::concept2command::Concept2Command::GetSerialNumber,
-                        concept2::concept2command::Concept2Command::GetSerialNumber,
-                        concept2::concept2command::Concept2Command::GetSerialNumber,
-                    ]);
-                    let result: HidResult<Vec<u8>> = device.open_device(&api).and_then(|dev| {
-                        concept2::hid_csafe::write_read_csafe_cmd(&dev, 2, &csafe_cmd)
-                    });
-                    match result {
-                        Ok(v) => {
-                            concept2::concept2response::parse_vec(&v).map(|r| println!("{:?}", r));
+            for monitor in concept2::discovery::discover(&api) {
+                println!(
+                    "Found a {} with serial number {}",
+                    monitor
+                        .model
+                        .map_or(String::from("monitor"), |m| format!("{:?}", m)),
+                    monitor.serial_number.as_deref().unwrap_or("unknown")
+                );
+                let csafe_cmd = concept2::csafe::CSAFEFrame::new(vec![
+                    concept2::concept2command::Concept2Command::ProprietaryCommand(vec![
+                        concept2::concept2command::Concept2ProprietaryCommand::GetWorkTime,
+                        concept2::concept2command::Concept2ProprietaryCommand::GetWorkDistance,
+                        concept2::concept2command::Concept2ProprietaryCommand::GetWorkoutType,
+                    ]),
+                    concept2::concept2command::Concept2Command::GetSerialNumber,
+                    concept2::concept2command::Concept2Command::GetSerialNumber,
+                    concept2::concept2command::Concept2Command::GetSerialNumber,
+                ]);
+                match monitor.open(&api) {
+                    Ok(dev) => {
+                        let mut transport = concept2::hid_csafe::HidTransport::new(dev);
+                        match concept2::transport::round_trip(
+                            &mut transport,
+                            &csafe_cmd,
+                            concept2::transport::DEFAULT_TIMEOUT,
+                        ) {
+                            Ok(frame) => println!("{:?}", frame.responses),
+                            Err(e) => print!("{}", e),
                         }
-                        Err(e) => {
-                            print!("{}", e);
-                        }
-                    };
-                    println!("");
-                }
+                    }
+                    Err(e) => {
+                        print!("{}", e);
+                    }
+                };
+                println!();
             }
         }
         Err(e) => {
@@ -0,0 +1,439 @@
+/// Async versions of `Transport`, `round_trip`, and `PerformanceMonitor`, for use with
+/// tokio. Only built with the `async` feature.
+///
+/// These mirror their blocking counterparts, but waiting on the machine gives the
+/// executor back, so one task can keep any number of ergs busy. `AsyncHidTransport`
+/// puts the device into non-blocking mode and checks for reports on a timer instead of
+/// blocking in `read_timeout`. Every future here can be dropped to cancel it; a response
+/// that arrives afterwards is discarded before the next request is sent. Timers come
+/// from tokio, so these must be run inside of a runtime with its time driver enabled.
+use std::convert::Infallible;
+use std::future::Future;
+use std::time::Duration;
+
+use hidapi::{HidDevice, HidResult};
+
+use crate::command::{self, Batch};
+use crate::concept2command::{Concept2Command, Concept2ProprietaryConfigCommand};
+use crate::concept2response::{self, CSAFEResponseFrame, StrokeStats};
+use crate::csafe::CSAFEFrame;
+use crate::error::Concept2Error;
+use crate::hid_csafe::{self, HidTransportError, ReportAssembler};
+use crate::monitor;
+use crate::simulator::Simulator;
+use crate::status::FrameStatus;
+use crate::transport::{self, Transport};
+use crate::units::{Distance, Measurement};
+use crate::version::{ErgType, VersionInfo};
+use crate::workout::{WorkoutState, WorkoutType};
+
+/// How often `AsyncHidTransport` checks for reports while it waits for a response.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// `Transport`, with futures in place of blocking calls.
+pub trait AsyncTransport {
+    type Error;
+
+    /// Throws away anything that has arrived but hasn't been received, with the same
+    /// rules as `Transport::discard`.
+    fn discard(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Sends one complete CSAFE frame.
+    fn send(&mut self, frame: &[u8]) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Waits up to `timeout` for one complete CSAFE frame from the machine, with the same
+    /// rules as `Transport::receive`. Returns `Ok(None)` on timeout.
+    fn receive(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Self::Error>> + Send;
+}
+
+impl<T: AsyncTransport + Send> AsyncTransport for &mut T {
+    type Error = T::Error;
+
+    fn discard(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).discard()
+    }
+
+    fn send(&mut self, frame: &[u8]) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).send(frame)
+    }
+
+    fn receive(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Self::Error>> + Send {
+        (**self).receive(timeout)
+    }
+}
+
+impl<T: AsyncTransport + Send> AsyncTransport for Box<T> {
+    type Error = T::Error;
+
+    fn discard(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).discard()
+    }
+
+    fn send(&mut self, frame: &[u8]) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).send(frame)
+    }
+
+    fn receive(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Self::Error>> + Send {
+        (**self).receive(timeout)
+    }
+}
+
+/// The simulator answers as soon as it's asked, so it never waits.
+impl AsyncTransport for Simulator {
+    type Error = Infallible;
+
+    async fn discard(&mut self) -> Result<(), Infallible> {
+        Transport::discard(self)
+    }
+
+    async fn send(&mut self, frame: &[u8]) -> Result<(), Infallible> {
+        Transport::send(self, frame)
+    }
+
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, Infallible> {
+        Transport::receive(self, timeout)
+    }
+}
+
+/// Sends `cmd` and parses the response. Anything left over from an earlier request,
+/// including one that was cancelled, is discarded first. The receive is also cut off
+/// after `timeout`, in case the transport doesn't keep to it.
+pub async fn round_trip<T: AsyncTransport + ?Sized>(
+    transport: &mut T,
+    cmd: &CSAFEFrame,
+    timeout: Duration,
+) -> Result<CSAFEResponseFrame, Concept2Error<T::Error>> {
+    transport
+        .discard()
+        .await
+        .map_err(Concept2Error::Transport)?;
+    transport
+        .send(&cmd.to_vec())
+        .await
+        .map_err(Concept2Error::Transport)?;
+    match tokio::time::timeout(timeout, transport.receive(timeout)).await {
+        Ok(Ok(Some(v))) => Ok(concept2response::parse_frame_at(&v, 0)?),
+        Ok(Ok(None)) | Err(_) => Err(Concept2Error::Timeout),
+        Ok(Err(e)) => Err(Concept2Error::Transport(e)),
+    }
+}
+
+/// An `AsyncTransport` over a Concept2 attached by USB.
+pub struct AsyncHidTransport {
+    device: HidDevice,
+    poll_interval: Duration,
+}
+
+impl AsyncHidTransport {
+    /// Puts `device` into non-blocking mode.
+    pub fn new(device: HidDevice) -> HidResult<AsyncHidTransport> {
+        device.set_blocking_mode(false)?;
+        Ok(AsyncHidTransport {
+            device,
+            poll_interval: POLL_INTERVAL,
+        })
+    }
+
+    /// How long to sleep between reads that come back empty.
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    pub fn device(&self) -> &HidDevice {
+        &self.device
+    }
+
+    /// The device is left in non-blocking mode.
+    pub fn into_inner(self) -> HidDevice {
+        self.device
+    }
+}
+
+impl AsyncTransport for AsyncHidTransport {
+    type Error = HidTransportError;
+
+    async fn discard(&mut self) -> Result<(), HidTransportError> {
+        Ok(hid_csafe::drain(&self.device)?)
+    }
+
+    /// Output reports are written straight away, even in non-blocking mode, so this
+    /// doesn't wait on anything.
+    async fn send(&mut self, frame: &[u8]) -> Result<(), HidTransportError> {
+        let msg = hid_csafe::to_report(frame)?;
+        self.device.write(msg.as_slice())?;
+        Ok(())
+    }
+
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, HidTransportError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut assembler = ReportAssembler::new();
+        let mut report: Vec<u8> = vec![0; hid_csafe::MSG_LENGTH];
+        loop {
+            let read = self.device.read(report.as_mut_slice())?;
+            if read > 0 {
+                if let Some(frame) = assembler.push(&report[..read]) {
+                    return Ok(Some(frame));
+                }
+            } else if tokio::time::Instant::now() >= deadline {
+                return Ok(None);
+            } else {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+    }
+}
+
+/// `PerformanceMonitor`, over an `AsyncTransport`.
+pub struct AsyncPerformanceMonitor<T: AsyncTransport> {
+    transport: T,
+    timeout: Duration,
+}
+
+impl<T: AsyncTransport> AsyncPerformanceMonitor<T> {
+    pub fn new(transport: T) -> AsyncPerformanceMonitor<T> {
+        AsyncPerformanceMonitor {
+            transport,
+            timeout: transport::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// How long to wait for each response.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Sends a command, or a batch of them, in a single frame, and returns the typed
+    /// responses. See `PerformanceMonitor::execute`.
+    pub async fn execute<B: Batch>(
+        &mut self,
+        batch: B,
+    ) -> Result<B::Output, Concept2Error<T::Error>> {
+        let commands = batch.commands();
+        let frame = round_trip(
+            &mut self.transport,
+            &CSAFEFrame::new(commands.clone()),
+            self.timeout,
+        )
+        .await?;
+        monitor::finish(&batch, &commands, frame)
+    }
+
+    /// Sends configuration commands, which have nothing to say in response.
+    pub async fn configure(
+        &mut self,
+        commands: Vec<Concept2ProprietaryConfigCommand>,
+    ) -> Result<(), Concept2Error<T::Error>> {
+        self.execute(vec![Concept2Command::ProprietaryConfigCommand(commands)])
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn status(&mut self) -> Result<FrameStatus, Concept2Error<T::Error>> {
+        self.execute(command::GetStatus).await
+    }
+
+    pub async fn version(&mut self) -> Result<VersionInfo, Concept2Error<T::Error>> {
+        self.execute(command::GetVersion).await
+    }
+
+    pub async fn serial_number(&mut self) -> Result<String, Concept2Error<T::Error>> {
+        self.execute(command::GetSerialNumber).await
+    }
+
+    pub async fn user_id(&mut self) -> Result<String, Concept2Error<T::Error>> {
+        self.execute(command::GetUserID).await
+    }
+
+    /// Lifetime distance rowed on this machine.
+    pub async fn odometer(&mut self) -> Result<Distance, Concept2Error<T::Error>> {
+        self.execute(command::GetOdometer).await
+    }
+
+    pub async fn heart_rate(&mut self) -> Result<u8, Concept2Error<T::Error>> {
+        self.execute(command::GetHeartRate).await
+    }
+
+    pub async fn power(&mut self) -> Result<Measurement, Concept2Error<T::Error>> {
+        self.execute(command::GetPower).await
+    }
+
+    /// Time into the current workout, or the current interval.
+    pub async fn work_time(&mut self) -> Result<Duration, Concept2Error<T::Error>> {
+        self.execute(command::GetWorkTime).await
+    }
+
+    /// Distance into the current workout, or the current interval.
+    pub async fn work_distance(&mut self) -> Result<Distance, Concept2Error<T::Error>> {
+        self.execute(command::GetWorkDistance).await
+    }
+
+    pub async fn workout_type(&mut self) -> Result<WorkoutType, Concept2Error<T::Error>> {
+        self.execute(command::GetWorkoutType).await
+    }
+
+    pub async fn workout_state(&mut self) -> Result<WorkoutState, Concept2Error<T::Error>> {
+        self.execute(command::GetWorkoutState).await
+    }
+
+    /// Time per 500 meters, for the most recent stroke.
+    pub async fn stroke_pace(&mut self) -> Result<Duration, Concept2Error<T::Error>> {
+        self.execute(command::GetStrokePace).await
+    }
+
+    /// Strokes per minute.
+    pub async fn stroke_rate(&mut self) -> Result<u8, Concept2Error<T::Error>> {
+        self.execute(command::GetStrokeRate).await
+    }
+
+    pub async fn stroke_stats(&mut self) -> Result<StrokeStats, Concept2Error<T::Error>> {
+        self.execute(command::GetStrokeStats).await
+    }
+
+    pub async fn drag_factor(&mut self) -> Result<u8, Concept2Error<T::Error>> {
+        self.execute(command::GetDragFactor).await
+    }
+
+    /// Whether this is a rower, a SkiErg, or a BikeErg.
+    pub async fn erg_type(&mut self) -> Result<ErgType, Concept2Error<T::Error>> {
+        self.execute(command::GetErgMachineType)
+            .await
+            .map(ErgType::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// A machine that never answers.
+    struct Silent;
+
+    impl super::AsyncTransport for Silent {
+        type Error = std::convert::Infallible;
+
+        async fn discard(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn send(&mut self, _frame: &[u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn receive(
+            &mut self,
+            _timeout: std::time::Duration,
+        ) -> Result<Option<Vec<u8>>, Self::Error> {
+            std::future::pending().await
+        }
+    }
+
+    /// A simulator that takes `delay` to answer.
+    struct Slow {
+        simulator: crate::simulator::Simulator,
+        delay: std::time::Duration,
+    }
+
+    impl super::AsyncTransport for Slow {
+        type Error = std::convert::Infallible;
+
+        async fn discard(&mut self) -> Result<(), Self::Error> {
+            super::AsyncTransport::discard(&mut self.simulator).await
+        }
+
+        async fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error> {
+            super::AsyncTransport::send(&mut self.simulator, frame).await
+        }
+
+        async fn receive(
+            &mut self,
+            timeout: std::time::Duration,
+        ) -> Result<Option<Vec<u8>>, Self::Error> {
+            tokio::time::sleep(self.delay).await;
+            super::AsyncTransport::receive(&mut self.simulator, timeout).await
+        }
+    }
+
+    #[test]
+    fn test_concurrent_monitors() {
+        let mut first = crate::simulator::Simulator::new();
+        first.set_serial_number("430000001");
+        let mut second = crate::simulator::Simulator::new();
+        second.set_serial_number("430000002");
+        second.start_rowing();
+        second.advance(crate::units::Duration::from_secs(10));
+        let mut first = super::AsyncPerformanceMonitor::new(first);
+        let mut second = super::AsyncPerformanceMonitor::new(second);
+        // One task per erg, as a server would have.
+        let (first, second) = block_on(async {
+            let first = tokio::spawn(async move { first.serial_number().await });
+            let second = tokio::spawn(async move {
+                second
+                    .execute((crate::command::GetSerialNumber, crate::command::GetWorkTime))
+                    .await
+            });
+            (first.await.unwrap(), second.await.unwrap())
+        });
+        assert_eq!(Ok(String::from("430000001")), first);
+        assert_eq!(
+            Ok((
+                String::from("430000002"),
+                crate::units::Duration::from_secs(10)
+            )),
+            second
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut pm = super::AsyncPerformanceMonitor::new(Silent);
+        pm.set_timeout(std::time::Duration::from_millis(10));
+        assert_eq!(
+            Err(crate::error::Concept2Error::Timeout),
+            block_on(pm.status())
+        );
+    }
+    #[test]
+    fn test_cancel() {
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_odometer(2172148);
+        let mut pm = super::AsyncPerformanceMonitor::new(Slow {
+            simulator,
+            delay: std::time::Duration::from_millis(20),
+        });
+        let (cancelled, odometer) = block_on(async {
+            let cancelled =
+                tokio::time::timeout(std::time::Duration::from_millis(5), pm.serial_number()).await;
+            (cancelled, pm.odometer().await)
+        });
+        assert!(cancelled.is_err());
+        // The serial number arrives late, and must not be taken as the odometer.
+        assert_eq!(Ok(crate::units::Distance::from_meters(2172148)), odometer);
+    }
+}
@@ -0,0 +1,351 @@
+/// Commands that know the type of their response.
+///
+/// Each of the unit structs here stands for one `Concept2Command` or
+/// `Concept2ProprietaryCommand`, and its `Command::Response` is the value carried by the
+/// matching response, so `pm.execute(GetOdometer)` is a `Distance` rather than a
+/// `Concept2Response` to be matched on. Tuples of commands are a `Batch` and are sent in
+/// one frame, coming back as a tuple of their responses:
+///
+/// `let (serial_number, time) = pm.execute((GetSerialNumber, GetWorkTime))?;`
+///
+/// A bare `Concept2Command` is a `Command` whose response is the untyped
+/// `Concept2Response`, and a `Vec<Concept2Command>` is a `Batch` that comes back as the
+/// whole `CSAFEResponseFrame`.
+use crate::concept2command::{Concept2Command, Concept2ProprietaryCommand};
+use crate::concept2response::{
+    CSAFEResponseFrame, Concept2Response, Concept2ResponseProprietary, HeartRateBelt, StrokeStats,
+};
+use crate::status::FrameStatus;
+use crate::units::{Distance, Duration, Measurement};
+use crate::version::{ErgMachineType, VersionInfo};
+use crate::workout::{
+    IntervalType, OperationalState, RowingState, StrokeState, WorkoutState, WorkoutType,
+};
+
+pub trait Command {
+    type Response;
+
+    /// The command as it's sent. Proprietary commands come in a wrapper of their own.
+    fn command(&self) -> Concept2Command;
+
+    /// Takes the value out of the response to this command. On failure, the identifier
+    /// of the response that doesn't belong.
+    fn response(response: Concept2Response) -> Result<Self::Response, u8>;
+}
+
+/// One or more commands that are sent together in a single frame.
+pub trait Batch {
+    type Output;
+
+    fn commands(&self) -> Vec<Concept2Command>;
+
+    /// Builds the output from a frame whose responses have already been matched up with
+    /// `commands`. On failure, the identifier of the response that doesn't belong.
+    fn output(&self, frame: CSAFEResponseFrame) -> Result<Self::Output, u8>;
+}
+
+impl Command for Concept2Command {
+    type Response = Concept2Response;
+
+    fn command(&self) -> Concept2Command {
+        self.clone()
+    }
+
+    fn response(response: Concept2Response) -> Result<Concept2Response, u8> {
+        Ok(response)
+    }
+}
+
+impl<C: Command> Batch for C {
+    type Output = C::Response;
+
+    fn commands(&self) -> Vec<Concept2Command> {
+        vec![self.command()]
+    }
+
+    fn output(&self, frame: CSAFEResponseFrame) -> Result<C::Response, u8> {
+        next_response(self, &mut frame.responses.into_iter())
+    }
+}
+
+impl Batch for Vec<Concept2Command> {
+    type Output = CSAFEResponseFrame;
+
+    fn commands(&self) -> Vec<Concept2Command> {
+        self.clone()
+    }
+
+    fn output(&self, frame: CSAFEResponseFrame) -> Result<CSAFEResponseFrame, u8> {
+        Ok(frame)
+    }
+}
+
+fn next_response<C: Command>(
+    command: &C,
+    responses: &mut impl Iterator<Item = Concept2Response>,
+) -> Result<C::Response, u8> {
+    match responses.next() {
+        Some(response) => C::response(response),
+        None => Err(command.command().identifier()),
+    }
+}
+
+macro_rules! batch {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: Command),+> Batch for ($($name,)+) {
+            type Output = ($($name::Response,)+);
+
+            fn commands(&self) -> Vec<Concept2Command> {
+                vec![$(self.$index.command()),+]
+            }
+
+            fn output(&self, frame: CSAFEResponseFrame) -> Result<Self::Output, u8> {
+                let mut responses = frame.responses.into_iter();
+                Ok(($(next_response(&self.$index, &mut responses)?,)+))
+            }
+        }
+    };
+}
+
+batch!(A 0);
+batch!(A 0, B 1);
+batch!(A 0, B 1, C 2);
+batch!(A 0, B 1, C 2, D 3);
+batch!(A 0, B 1, C 2, D 3, E 4);
+batch!(A 0, B 1, C 2, D 3, E 4, F 5);
+batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+batch!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Defines a command that's sent on its own. A response that carries several values
+/// comes back as a tuple of them.
+macro_rules! command {
+    ($(#[$meta:meta])* $name:ident => ($a:ty, $b:ty)) => {
+        command!(@define $(#[$meta])* $name => ($a, $b), (a, b) => (a, b));
+    };
+    ($(#[$meta:meta])* $name:ident => ($a:ty, $b:ty, $c:ty)) => {
+        command!(@define $(#[$meta])* $name => ($a, $b, $c), (a, b, c) => (a, b, c));
+    };
+    ($(#[$meta:meta])* $name:ident => $response:ty) => {
+        command!(@define $(#[$meta])* $name => $response, (value) => value);
+    };
+    (@define $(#[$meta:meta])* $name:ident => $response:ty,
+        ($($binding:ident),+) => $value:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+
+        impl Command for $name {
+            type Response = $response;
+
+            fn command(&self) -> Concept2Command {
+                Concept2Command::$name
+            }
+
+            fn response(response: Concept2Response) -> Result<$response, u8> {
+                match response {
+                    Concept2Response::$name($($binding),+) => Ok($value),
+                    r => Err(r.identifier()),
+                }
+            }
+        }
+    };
+}
+
+/// Defines a command that's sent inside of the proprietary wrapper.
+macro_rules! proprietary_command {
+    ($(#[$meta:meta])* $name:ident => $response:ty) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+
+        proprietary_command!(@impl $name => $response, |self| Concept2ProprietaryCommand::$name);
+    };
+    // A command that takes an argument, which is sent along with it.
+    ($(#[$meta:meta])* $name:ident($field:ty) => $response:ty) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name(pub $field);
+
+        proprietary_command!(
+            @impl $name => $response, |self| Concept2ProprietaryCommand::$name(self.0)
+        );
+    };
+    (@impl $name:ident => $response:ty, |$self:ident| $command:expr) => {
+        impl Command for $name {
+            type Response = $response;
+
+            fn command(&$self) -> Concept2Command {
+                Concept2Command::ProprietaryCommand(vec![$command])
+            }
+
+            fn response(response: Concept2Response) -> Result<$response, u8> {
+                let identifier = response.identifier();
+                match response {
+                    Concept2Response::ProprietaryCommand(mut responses) => {
+                        match (responses.pop(), responses.is_empty()) {
+                            (Some(Concept2ResponseProprietary::$name(value)), true) => Ok(value),
+                            (Some(r), _) => Err(r.identifier()),
+                            (None, _) => Err(identifier),
+                        }
+                    }
+                    _ => Err(identifier),
+                }
+            }
+        }
+    };
+}
+
+command!(GetStatus => FrameStatus);
+command!(GetVersion => VersionInfo);
+command!(GetUserID => String);
+command!(
+    /// 0 for metric, 1 for English.
+    GetUnits => u8
+);
+command!(GetSerialNumber => String);
+command!(
+    /// Lifetime distance rowed on this machine.
+    GetOdometer => Distance
+);
+command!(GetErrorCode => u32);
+command!(GetTWork => Duration);
+command!(GetHorizontal => Distance);
+command!(GetCalories => u16);
+command!(
+    /// Program number and level.
+    GetProgram => (u8, u8)
+);
+command!(
+    /// Seconds per unit of distance.
+    GetPace => Measurement
+);
+command!(GetCadence => Measurement);
+command!(
+    /// Weight, age, and gender.
+    GetUserInfo => (Measurement, u8, u8)
+);
+command!(
+    /// Beats per minute. 0 means that no heart rate monitor is attached.
+    GetHeartRate => u8
+);
+command!(GetPower => Measurement);
+
+proprietary_command!(GetStrokeStats => StrokeStats);
+proprietary_command!(GetHeartRateBeltInfo => HeartRateBelt);
+proprietary_command!(
+    /// Time into the current workout, or the current interval.
+    GetWorkTime => Duration
+);
+proprietary_command!(
+    /// Distance into the current workout, or the current interval.
+    GetWorkDistance => Distance
+);
+proprietary_command!(GetWorkoutType => WorkoutType);
+proprietary_command!(GetDisplayType => u8);
+proprietary_command!(GetDisplayUnits => u8);
+proprietary_command!(GetWorkoutState => WorkoutState);
+proprietary_command!(GetIntervalType => IntervalType);
+proprietary_command!(GetOperationalState => OperationalState);
+proprietary_command!(GetRowingState => RowingState);
+proprietary_command!(
+    /// Percent of a full charge.
+    GetBatteryLevel => u8
+);
+proprietary_command!(GetWorkoutIntervalCount => u8);
+proprietary_command!(
+    /// Time per 500 meters, for the most recent stroke.
+    GetStrokePace => Duration
+);
+proprietary_command!(
+    /// Watts.
+    GetStrokePower => u32
+);
+proprietary_command!(
+    /// Calories per hour.
+    GetStrokeCaloricBurnRate => u32
+);
+proprietary_command!(GetSplitAvgPace => Duration);
+proprietary_command!(GetSplitAvgPower => u32);
+proprietary_command!(GetTotalAvgPace => Duration);
+proprietary_command!(GetTotalAvgPower => u32);
+proprietary_command!(GetTotalAvgCalories => u32);
+proprietary_command!(
+    /// Strokes per minute.
+    GetStrokeRate => u8
+);
+proprietary_command!(GetSplitAvgStrokeRate => u8);
+proprietary_command!(GetTotalAvgStrokeRate => u8);
+proprietary_command!(
+    /// Beats per minute.
+    GetAvgHeartRate => u8
+);
+proprietary_command!(GetLastSplitTime => Duration);
+proprietary_command!(GetLastSplitDistance => Distance);
+proprietary_command!(GetTargetPaceTime => Duration);
+proprietary_command!(GetStrokeState => StrokeState);
+proprietary_command!(GetDragFactor => u8);
+proprietary_command!(GetErrorType => u8);
+proprietary_command!(GetErrorValue => u16);
+proprietary_command!(GetRestTime => Duration);
+proprietary_command!(GetErgMachineType => ErgMachineType);
+
+proprietary_command!(
+    /// Up to this many bytes of the force curve. See
+    /// `Concept2ProprietaryCommand::GetForcePlotData`.
+    GetForcePlotData(u8) => Vec<u16>
+);
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_response() {
+        use super::Command;
+        use crate::concept2response::{Concept2Response, Concept2ResponseProprietary};
+        assert_eq!(
+            Ok(140),
+            super::GetHeartRate::response(Concept2Response::GetHeartRate(140))
+        );
+        assert_eq!(
+            Err(0xb4),
+            super::GetHeartRate::response(Concept2Response::GetPower(crate::units::Measurement {
+                value: 200,
+                units: crate::units::Units::Watts,
+            }))
+        );
+        assert_eq!(
+            Ok((4, 2)),
+            super::GetProgram::response(Concept2Response::GetProgram(4, 2))
+        );
+        assert_eq!(
+            Err(0xa3),
+            super::GetProgram::response(Concept2Response::GetCalories(12))
+        );
+        assert_eq!(
+            Ok(120),
+            super::GetDragFactor::response(Concept2Response::ProprietaryCommand(vec![
+                Concept2ResponseProprietary::GetDragFactor(120)
+            ]))
+        );
+        assert_eq!(
+            Err(0xb3),
+            super::GetDragFactor::response(Concept2Response::ProprietaryCommand(vec![
+                Concept2ResponseProprietary::GetStrokeRate(24)
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        use super::Batch;
+        let batch = (super::GetSerialNumber, super::GetWorkTime);
+        assert_eq!(
+            vec![
+                crate::concept2command::Concept2Command::GetSerialNumber,
+                crate::concept2command::Concept2Command::ProprietaryCommand(vec![
+                    crate::concept2command::Concept2ProprietaryCommand::GetWorkTime
+                ]),
+            ],
+            batch.commands()
+        );
+    }
+}
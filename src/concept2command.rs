@@ -1,68 +1,410 @@
 use crate::consts;
+use crate::units::{self, Duration, Units};
+use crate::workout::{DurationType, IntervalType, ScreenType, WorkoutType};
 
+/// Commands that can be sent to the machine inside of a `CSAFEFrame`. "Short commands"
+/// are a single identifier byte with no data. "Long commands" (the `Set` commands,
+/// `AutoUpload`, and the two proprietary wrappers) are followed by a byte count and
+/// their data.
+/// Multi-byte values are sent little-endian, and values with units are followed by a
+/// CSAFE units byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Concept2Command {
+    /// Bit flags controlling what the machine sends without being asked.
+    AutoUpload(u8),
+    /// Number of digits in the user ID.
+    SetIDDigits(u8),
+    /// Hour, minute, and second.
+    SetTime(u8, u8, u8),
+    /// Years since 1900, month, and day.
+    SetDate(u8, u8, u8),
+    /// Seconds of inactivity before the machine gives up on the workout.
+    SetTimeout(u8),
+    /// Sent as hours, minutes, and seconds; fractions of a second are dropped.
+    SetTWork(Duration),
+    SetHorizontal(u16, Units),
+    SetCalories(u16),
+    /// Program number and level.
+    SetProgram(u8, u8),
+    /// Weight, weight units, age, and gender.
+    SetUserInfo(u16, Units, u8, u8),
+    SetPower(u16, Units),
     GetStatus,
+    Reset,
+    GoIdle,
+    GoHaveID,
+    GoInUse,
+    GoFinished,
+    GoReady,
+    BadID,
     GetVersion,
     GetUserID,
+    GetUnits,
     GetSerialNumber,
     GetOdometer,
+    GetErrorCode,
+    GetTWork,
+    GetHorizontal,
+    GetCalories,
+    GetProgram,
+    GetPace,
+    GetCadence,
+    GetUserInfo,
+    GetHeartRate,
+    GetPower,
     ProprietaryCommand(Vec<Concept2ProprietaryCommand>),
+    ProprietaryConfigCommand(Vec<Concept2ProprietaryConfigCommand>),
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// Concept2-specific "get" commands, sent inside of `Concept2Command::ProprietaryCommand`.
+/// All of them are single bytes except `GetStrokeStats`, `GetForcePlotData`, and
+/// `GetHeartRateBeltInfo`, which are long commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Concept2ProprietaryCommand {
+    GetStrokeStats,
+    /// The next part of the force curve of the current drive, up to this many bytes. The
+    /// PM sends at most 32 at a time.
+    GetForcePlotData(u8),
+    /// The heart rate belt that's paired with the PM.
+    GetHeartRateBeltInfo,
     GetWorkoutType,
+    GetDisplayType,
+    GetDisplayUnits,
+    GetWorkoutState,
+    GetIntervalType,
+    GetOperationalState,
+    GetRowingState,
+    GetBatteryLevel,
+    GetWorkoutIntervalCount,
     GetWorkTime,
     GetWorkDistance,
+    GetStrokePace,
+    GetStrokePower,
+    GetStrokeCaloricBurnRate,
+    GetSplitAvgPace,
+    GetSplitAvgPower,
+    GetTotalAvgPace,
+    GetTotalAvgPower,
+    GetTotalAvgCalories,
+    GetStrokeRate,
+    GetSplitAvgStrokeRate,
+    GetTotalAvgStrokeRate,
+    GetAvgHeartRate,
+    GetLastSplitTime,
+    GetLastSplitDistance,
+    GetTargetPaceTime,
+    GetStrokeState,
+    GetDragFactor,
+    GetErrorType,
+    GetErrorValue,
+    GetRestTime,
+    GetErgMachineType,
 }
 
-impl From<Concept2ProprietaryCommand> for u8 {
-    fn from(c: Concept2ProprietaryCommand) -> Self {
-        match c {
+impl Concept2ProprietaryCommand {
+    pub fn identifier(&self) -> u8 {
+        match self {
+            Concept2ProprietaryCommand::GetStrokeStats => consts::csafe_commands::GET_STROKE_STATS,
+            Concept2ProprietaryCommand::GetForcePlotData(_) => {
+                consts::csafe_commands::GET_FORCE_PLOT_DATA
+            }
+            Concept2ProprietaryCommand::GetHeartRateBeltInfo => {
+                consts::csafe_commands::GET_EXTENDED_HR_BELT_INFO
+            }
+            Concept2ProprietaryCommand::GetWorkoutType => consts::csafe_commands::GET_WORKOUT_TYPE,
+            Concept2ProprietaryCommand::GetDisplayType => consts::csafe_commands::GET_DISPLAY_TYPE,
+            Concept2ProprietaryCommand::GetDisplayUnits => {
+                consts::csafe_commands::GET_DISPLAY_UNITS
+            }
+            Concept2ProprietaryCommand::GetWorkoutState => {
+                consts::csafe_commands::GET_WORKOUT_STATE
+            }
+            Concept2ProprietaryCommand::GetIntervalType => {
+                consts::csafe_commands::GET_INTERVAL_TYPE
+            }
+            Concept2ProprietaryCommand::GetOperationalState => {
+                consts::csafe_commands::GET_OPERATIONAL_STATE
+            }
+            Concept2ProprietaryCommand::GetRowingState => consts::csafe_commands::GET_ROWING_STATE,
+            Concept2ProprietaryCommand::GetBatteryLevel => {
+                consts::csafe_commands::GET_BATTERY_LEVEL
+            }
+            Concept2ProprietaryCommand::GetWorkoutIntervalCount => {
+                consts::csafe_commands::GET_WORKOUT_INTERVAL_COUNT
+            }
             Concept2ProprietaryCommand::GetWorkTime => consts::csafe_commands::GET_WORK_TIME,
             Concept2ProprietaryCommand::GetWorkDistance => {
                 consts::csafe_commands::GET_WORK_DISTANCE
             }
-            Concept2ProprietaryCommand::GetWorkoutType => consts::csafe_commands::GET_WORKOUT_TYPE,
+            Concept2ProprietaryCommand::GetStrokePace => consts::csafe_commands::GET_STROKE_PACE,
+            Concept2ProprietaryCommand::GetStrokePower => consts::csafe_commands::GET_STROKE_POWER,
+            Concept2ProprietaryCommand::GetStrokeCaloricBurnRate => {
+                consts::csafe_commands::GET_STROKE_CALORIC_BURN_RATE
+            }
+            Concept2ProprietaryCommand::GetSplitAvgPace => {
+                consts::csafe_commands::GET_SPLIT_AVG_PACE
+            }
+            Concept2ProprietaryCommand::GetSplitAvgPower => {
+                consts::csafe_commands::GET_SPLIT_AVG_POWER
+            }
+            Concept2ProprietaryCommand::GetTotalAvgPace => {
+                consts::csafe_commands::GET_TOTAL_AVG_PACE
+            }
+            Concept2ProprietaryCommand::GetTotalAvgPower => {
+                consts::csafe_commands::GET_TOTAL_AVG_POWER
+            }
+            Concept2ProprietaryCommand::GetTotalAvgCalories => {
+                consts::csafe_commands::GET_TOTAL_AVG_CALORIES
+            }
+            Concept2ProprietaryCommand::GetStrokeRate => consts::csafe_commands::GET_STROKE_RATE,
+            Concept2ProprietaryCommand::GetSplitAvgStrokeRate => {
+                consts::csafe_commands::GET_SPLIT_AVG_STROKE_RATE
+            }
+            Concept2ProprietaryCommand::GetTotalAvgStrokeRate => {
+                consts::csafe_commands::GET_TOTAL_AVG_STROKE_RATE
+            }
+            Concept2ProprietaryCommand::GetAvgHeartRate => {
+                consts::csafe_commands::GET_AVG_HEART_RATE
+            }
+            Concept2ProprietaryCommand::GetLastSplitTime => {
+                consts::csafe_commands::GET_LAST_SPLIT_TIME
+            }
+            Concept2ProprietaryCommand::GetLastSplitDistance => {
+                consts::csafe_commands::GET_LAST_SPLIT_DISTANCE
+            }
+            Concept2ProprietaryCommand::GetTargetPaceTime => {
+                consts::csafe_commands::GET_TARGET_PACE_TIME
+            }
+            Concept2ProprietaryCommand::GetStrokeState => consts::csafe_commands::GET_STROKE_STATE,
+            Concept2ProprietaryCommand::GetDragFactor => consts::csafe_commands::GET_DRAG_FACTOR,
+            Concept2ProprietaryCommand::GetErrorType => consts::csafe_commands::GET_ERROR_TYPE,
+            Concept2ProprietaryCommand::GetErrorValue => consts::csafe_commands::GET_ERROR_VALUE,
+            Concept2ProprietaryCommand::GetRestTime => consts::csafe_commands::GET_REST_TIME,
+            Concept2ProprietaryCommand::GetErgMachineType => {
+                consts::csafe_commands::GET_ERG_MACHINE_TYPE
+            }
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u8>> {
+        match self {
+            // The PM expects a single, always-zero byte of data with these.
+            Concept2ProprietaryCommand::GetStrokeStats
+            | Concept2ProprietaryCommand::GetHeartRateBeltInfo => {
+                Box::new(vec![self.identifier(), 0x01, 0x00].into_iter())
+            }
+            Concept2ProprietaryCommand::GetForcePlotData(bytes) => {
+                Box::new(vec![self.identifier(), 0x01, *bytes].into_iter())
+            }
+            _ => Box::new(std::iter::once(self.identifier())),
         }
     }
 }
 
+/// Converting a proprietary command to a `u8` gives its identifier.
+impl From<Concept2ProprietaryCommand> for u8 {
+    fn from(c: Concept2ProprietaryCommand) -> Self {
+        c.identifier()
+    }
+}
+
 impl<'a> From<&'a Concept2ProprietaryCommand> for u8 {
     fn from(c: &'a Concept2ProprietaryCommand) -> Self {
-        match c {
-            Concept2ProprietaryCommand::GetWorkTime => consts::csafe_commands::GET_WORK_TIME,
-            Concept2ProprietaryCommand::GetWorkDistance => {
-                consts::csafe_commands::GET_WORK_DISTANCE
+        c.identifier()
+    }
+}
+
+/// Concept2-specific configuration commands, sent inside of
+/// `Concept2Command::ProprietaryConfigCommand`. These are how workouts get programmed
+/// onto the PM. Unlike everything else, their multi-byte values are big-endian.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Concept2ProprietaryConfigCommand {
+    SetWorkoutType(WorkoutType),
+    SetWorkoutDuration(DurationType, u32),
+    /// Sent in whole seconds.
+    SetRestDuration(Duration),
+    SetSplitDuration(DurationType, u32),
+    /// Time per 500 meters.
+    SetTargetPaceTime(Duration),
+    /// The screen value is interpreted according to the screen type - see
+    /// `workout::WorkoutScreen` for `ScreenType::Workout`.
+    SetScreenState(ScreenType, u8),
+    /// Enables or disables workout programming mode.
+    ConfigureWorkout(bool),
+    SetIntervalType(IntervalType),
+    /// Selects which interval of a variable interval workout the following
+    /// commands apply to, starting at 0.
+    SetWorkoutIntervalCount(u8),
+}
+
+impl Concept2ProprietaryConfigCommand {
+    pub fn identifier(&self) -> u8 {
+        match self {
+            Concept2ProprietaryConfigCommand::SetWorkoutType(_) => {
+                consts::csafe_commands::SET_WORKOUT_TYPE
             }
-            Concept2ProprietaryCommand::GetWorkoutType => consts::csafe_commands::GET_WORKOUT_TYPE,
+            Concept2ProprietaryConfigCommand::SetWorkoutDuration(..) => {
+                consts::csafe_commands::SET_WORKOUT_DURATION
+            }
+            Concept2ProprietaryConfigCommand::SetRestDuration(_) => {
+                consts::csafe_commands::SET_REST_DURATION
+            }
+            Concept2ProprietaryConfigCommand::SetSplitDuration(..) => {
+                consts::csafe_commands::SET_SPLIT_DURATION
+            }
+            Concept2ProprietaryConfigCommand::SetTargetPaceTime(_) => {
+                consts::csafe_commands::SET_TARGET_PACE_TIME
+            }
+            Concept2ProprietaryConfigCommand::SetScreenState(..) => {
+                consts::csafe_commands::SET_SCREEN_STATE
+            }
+            Concept2ProprietaryConfigCommand::ConfigureWorkout(_) => {
+                consts::csafe_commands::CONFIGURE_WORKOUT
+            }
+            Concept2ProprietaryConfigCommand::SetIntervalType(_) => {
+                consts::csafe_commands::SET_INTERVAL_TYPE
+            }
+            Concept2ProprietaryConfigCommand::SetWorkoutIntervalCount(_) => {
+                consts::csafe_commands::SET_WORKOUT_INTERVAL_COUNT
+            }
+        }
+    }
+
+    /// The data of the command, not including its byte count.
+    pub fn data(&self) -> Vec<u8> {
+        match self {
+            Concept2ProprietaryConfigCommand::SetWorkoutType(workout_type) => {
+                vec![u8::from(*workout_type)]
+            }
+            Concept2ProprietaryConfigCommand::SetWorkoutIntervalCount(x) => vec![*x],
+            Concept2ProprietaryConfigCommand::SetWorkoutDuration(duration_type, x)
+            | Concept2ProprietaryConfigCommand::SetSplitDuration(duration_type, x) => {
+                std::iter::once(u8::from(*duration_type))
+                    .chain(x.to_be_bytes().iter().copied())
+                    .collect()
+            }
+            Concept2ProprietaryConfigCommand::SetRestDuration(d) => {
+                let secs = std::cmp::min(d.as_secs(), u64::from(u16::MAX)) as u16;
+                secs.to_be_bytes().to_vec()
+            }
+            Concept2ProprietaryConfigCommand::SetTargetPaceTime(d) => {
+                units::to_hundredths(*d).to_be_bytes().to_vec()
+            }
+            Concept2ProprietaryConfigCommand::SetScreenState(screen_type, value) => {
+                vec![u8::from(*screen_type), *value]
+            }
+            Concept2ProprietaryConfigCommand::ConfigureWorkout(enabled) => vec![*enabled as u8],
+            Concept2ProprietaryConfigCommand::SetIntervalType(x) => vec![u8::from(*x)],
         }
     }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u8>> {
+        let data = self.data();
+        Box::new(
+            std::iter::once(self.identifier())
+                .chain(std::iter::once(data.len() as u8))
+                .chain(data),
+        )
+    }
 }
 
 impl Concept2Command {
-    pub fn iter(&self) -> Box<dyn Iterator<Item = u8> + '_> {
+    /// The identifier byte of the command. For `ProprietaryCommand`, this is the
+    /// wrapper's identifier rather than the identifiers of the commands inside of it.
+    pub fn identifier(&self) -> u8 {
         match self {
-            Concept2Command::GetStatus => {
-                Box::new(std::iter::once(consts::csafe_commands::GET_STATUS))
+            Concept2Command::AutoUpload(_) => consts::csafe_commands::AUTO_UPLOAD,
+            Concept2Command::SetIDDigits(_) => consts::csafe_commands::SET_ID_DIGITS,
+            Concept2Command::SetTime(..) => consts::csafe_commands::SET_TIME,
+            Concept2Command::SetDate(..) => consts::csafe_commands::SET_DATE,
+            Concept2Command::SetTimeout(_) => consts::csafe_commands::SET_TIMEOUT,
+            Concept2Command::SetTWork(..) => consts::csafe_commands::SET_TWORK,
+            Concept2Command::SetHorizontal(..) => consts::csafe_commands::SET_HORIZONTAL,
+            Concept2Command::SetCalories(_) => consts::csafe_commands::SET_CALORIES,
+            Concept2Command::SetProgram(..) => consts::csafe_commands::SET_PROGRAM,
+            Concept2Command::SetUserInfo(..) => consts::csafe_commands::SET_USER_INFO,
+            Concept2Command::SetPower(..) => consts::csafe_commands::SET_POWER,
+            Concept2Command::GetStatus => consts::csafe_commands::GET_STATUS,
+            Concept2Command::Reset => consts::csafe_commands::RESET,
+            Concept2Command::GoIdle => consts::csafe_commands::GO_IDLE,
+            Concept2Command::GoHaveID => consts::csafe_commands::GO_HAVE_ID,
+            Concept2Command::GoInUse => consts::csafe_commands::GO_IN_USE,
+            Concept2Command::GoFinished => consts::csafe_commands::GO_FINISHED,
+            Concept2Command::GoReady => consts::csafe_commands::GO_READY,
+            Concept2Command::BadID => consts::csafe_commands::BAD_ID,
+            Concept2Command::GetVersion => consts::csafe_commands::GET_VERSION,
+            Concept2Command::GetUserID => consts::csafe_commands::GET_USER_ID,
+            Concept2Command::GetUnits => consts::csafe_commands::GET_UNITS,
+            Concept2Command::GetSerialNumber => consts::csafe_commands::GET_SERIAL_NUMBER,
+            Concept2Command::GetOdometer => consts::csafe_commands::GET_ODOMETER,
+            Concept2Command::GetErrorCode => consts::csafe_commands::GET_ERROR_CODE,
+            Concept2Command::GetTWork => consts::csafe_commands::GET_TWORK,
+            Concept2Command::GetHorizontal => consts::csafe_commands::GET_HORIZONTAL,
+            Concept2Command::GetCalories => consts::csafe_commands::GET_CALORIES,
+            Concept2Command::GetProgram => consts::csafe_commands::GET_PROGRAM,
+            Concept2Command::GetPace => consts::csafe_commands::GET_PACE,
+            Concept2Command::GetCadence => consts::csafe_commands::GET_CADENCE,
+            Concept2Command::GetUserInfo => consts::csafe_commands::GET_USER_INFO,
+            Concept2Command::GetHeartRate => consts::csafe_commands::GET_HEART_RATE,
+            Concept2Command::GetPower => consts::csafe_commands::GET_POWER,
+            Concept2Command::ProprietaryCommand(_) => consts::csafe_commands::PROPRIETARY_COMMAND,
+            Concept2Command::ProprietaryConfigCommand(_) => {
+                consts::csafe_commands::PROPRIETARY_CONFIG_COMMAND
             }
-            Concept2Command::GetVersion => {
-                Box::new(std::iter::once(consts::csafe_commands::GET_VERSION))
+        }
+    }
+
+    /// The data of a long command, not including its byte count. Short commands
+    /// have no data and return `None`.
+    pub fn data(&self) -> Option<Vec<u8>> {
+        match self {
+            Concept2Command::AutoUpload(x)
+            | Concept2Command::SetIDDigits(x)
+            | Concept2Command::SetTimeout(x) => Some(vec![*x]),
+            Concept2Command::SetTime(a, b, c) | Concept2Command::SetDate(a, b, c) => {
+                Some(vec![*a, *b, *c])
             }
-            Concept2Command::GetUserID => {
-                Box::new(std::iter::once(consts::csafe_commands::GET_USER_ID))
+            Concept2Command::SetTWork(d) => {
+                let (hours, minutes, seconds) = units::to_hms(*d);
+                Some(vec![hours, minutes, seconds])
             }
-            Concept2Command::GetSerialNumber => {
-                Box::new(std::iter::once(consts::csafe_commands::GET_SERIAL_NUMBER))
+            Concept2Command::SetHorizontal(value, units)
+            | Concept2Command::SetPower(value, units) => Some(
+                value
+                    .to_le_bytes()
+                    .iter()
+                    .copied()
+                    .chain(std::iter::once(u8::from(*units)))
+                    .collect(),
+            ),
+            Concept2Command::SetCalories(calories) => Some(calories.to_le_bytes().to_vec()),
+            Concept2Command::SetProgram(program, level) => Some(vec![*program, *level]),
+            Concept2Command::SetUserInfo(weight, units, age, gender) => Some(
+                weight
+                    .to_le_bytes()
+                    .iter()
+                    .copied()
+                    .chain(vec![u8::from(*units), *age, *gender])
+                    .collect(),
+            ),
+            Concept2Command::ProprietaryCommand(vec) => {
+                Some(vec.iter().flat_map(|c| c.iter()).collect())
             }
-            Concept2Command::GetOdometer => {
-                Box::new(std::iter::once(consts::csafe_commands::GET_ODOMETER))
+            Concept2Command::ProprietaryConfigCommand(vec) => {
+                Some(vec.iter().flat_map(|c| c.iter()).collect())
             }
-            Concept2Command::ProprietaryCommand(vec) => Box::new(
-                std::iter::once(consts::csafe_commands::PROPRIETARY_COMMAND)
-                    .chain(std::iter::once(vec.len() as u8))
-                    .chain(vec.iter().map(u8::from)),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u8> + '_> {
+        match self.data() {
+            Some(data) => Box::new(
+                std::iter::once(self.identifier())
+                    .chain(std::iter::once(data.len() as u8))
+                    .chain(data),
             ),
+            None => Box::new(std::iter::once(self.identifier())),
         }
     }
 }
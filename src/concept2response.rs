@@ -1,170 +1,828 @@
 /// Library for parsing vectors of bytes from the Concept2 machine into
 /// Concept2Response structs.
 use crate::consts;
-use std::convert::TryInto;
+use crate::csafe::FrameAddress;
+use crate::error::ParseError;
+use crate::status::FrameStatus;
+use crate::units::{self, Distance, Duration, Measurement, Units};
+use crate::version::{ErgMachineType, VersionInfo};
+use crate::workout::{
+    IntervalType, OperationalState, RowingState, StrokeState, WorkoutState, WorkoutType,
+};
 
 /// All implemented (so far) responses that can be parsed from the machine.
 /// Note `ProprietaryCommand`, which contains a variety of Concept2-specific
 /// commands that are not part of the CSAFE specification.
 #[derive(Debug, PartialEq, Eq)]
 pub enum Concept2Response {
-    GetStatus,
-    GetVersion,
+    AutoUpload,
+    SetIDDigits,
+    SetTime,
+    SetDate,
+    SetTimeout,
+    SetTWork,
+    SetHorizontal,
+    SetCalories,
+    SetProgram,
+    SetUserInfo,
+    SetPower,
+    GetStatus(FrameStatus),
+    Reset,
+    GoIdle,
+    GoHaveID,
+    GoInUse,
+    GoFinished,
+    GoReady,
+    BadID,
+    GetVersion(VersionInfo),
     GetUserID(String),
+    /// 0 for metric, 1 for English.
+    GetUnits(u8),
     GetSerialNumber(String),
-    GetOdometer(u32, u8),
+    GetOdometer(Distance),
+    GetErrorCode(u32),
+    GetTWork(Duration),
+    GetHorizontal(Distance),
+    GetCalories(u16),
+    /// Program number and level.
+    GetProgram(u8, u8),
+    /// Seconds per unit of distance.
+    GetPace(Measurement),
+    GetCadence(Measurement),
+    /// Weight, age, and gender.
+    GetUserInfo(Measurement, u8, u8),
+    /// Beats per minute. 0 means that no heart rate monitor is attached.
+    GetHeartRate(u8),
+    GetPower(Measurement),
     ProprietaryCommand(Vec<Concept2ResponseProprietary>),
+    ProprietaryConfigCommand(Vec<Concept2ResponseProprietaryConfig>),
+}
+
+/// A whole response frame: the machine's status, followed by the responses to each of
+/// the commands in the frame that we sent.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CSAFEResponseFrame {
+    /// For Extended frames, `source` is the machine that answered.
+    pub address: Option<FrameAddress>,
+    pub status: FrameStatus,
+    pub responses: Vec<Concept2Response>,
 }
 
 /// Proprietary commands that are not part of the CSAFE specification. These
 /// commands are wrapped inside a special format byte. The Concept2 spec refers
 /// to this idea as a "long command" - that is, commands containing more than one
 /// byte.
+/// Paces are per 500 meters.
 #[derive(Debug, PartialEq, Eq)]
 pub enum Concept2ResponseProprietary {
-    GetWorkTime(u32, u8),
-    GetWorkDistance(u32, u8),
-    GetWorkoutType(u8),
+    GetStrokeStats(StrokeStats),
+    /// Force samples, in pounds, from the part of the force curve that was read.
+    GetForcePlotData(Vec<u16>),
+    GetHeartRateBeltInfo(HeartRateBelt),
+    GetWorkTime(Duration),
+    GetWorkDistance(Distance),
+    GetWorkoutType(WorkoutType),
+    GetDisplayType(u8),
+    GetDisplayUnits(u8),
+    GetWorkoutState(WorkoutState),
+    GetIntervalType(IntervalType),
+    GetOperationalState(OperationalState),
+    GetRowingState(RowingState),
+    /// Percent of a full charge.
+    GetBatteryLevel(u8),
+    GetWorkoutIntervalCount(u8),
+    GetStrokePace(Duration),
+    /// Watts.
+    GetStrokePower(u32),
+    /// Calories per hour.
+    GetStrokeCaloricBurnRate(u32),
+    GetSplitAvgPace(Duration),
+    GetSplitAvgPower(u32),
+    GetTotalAvgPace(Duration),
+    GetTotalAvgPower(u32),
+    GetTotalAvgCalories(u32),
+    /// Strokes per minute.
+    GetStrokeRate(u8),
+    GetSplitAvgStrokeRate(u8),
+    GetTotalAvgStrokeRate(u8),
+    /// Beats per minute.
+    GetAvgHeartRate(u8),
+    GetLastSplitTime(Duration),
+    GetLastSplitDistance(Distance),
+    GetTargetPaceTime(Duration),
+    GetStrokeState(StrokeState),
+    GetDragFactor(u8),
+    GetErrorType(u8),
+    GetErrorValue(u16),
+    GetRestTime(Duration),
+    GetErgMachineType(ErgMachineType),
+}
+
+/// Acknowledgements of the commands inside of a `ProprietaryConfigCommand`. None of
+/// them carry any data.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Concept2ResponseProprietaryConfig {
+    SetWorkoutType,
+    SetWorkoutDuration,
+    SetRestDuration,
+    SetSplitDuration,
+    SetTargetPaceTime,
+    SetScreenState,
+    ConfigureWorkout,
+    SetIntervalType,
+    SetWorkoutIntervalCount,
+}
+
+impl Concept2Response {
+    /// The identifier of the command that this is a response to. For the wrappers, this
+    /// is the wrapper's identifier.
+    pub fn identifier(&self) -> u8 {
+        match self {
+            Concept2Response::AutoUpload => consts::csafe_commands::AUTO_UPLOAD,
+            Concept2Response::SetIDDigits => consts::csafe_commands::SET_ID_DIGITS,
+            Concept2Response::SetTime => consts::csafe_commands::SET_TIME,
+            Concept2Response::SetDate => consts::csafe_commands::SET_DATE,
+            Concept2Response::SetTimeout => consts::csafe_commands::SET_TIMEOUT,
+            Concept2Response::SetTWork => consts::csafe_commands::SET_TWORK,
+            Concept2Response::SetHorizontal => consts::csafe_commands::SET_HORIZONTAL,
+            Concept2Response::SetCalories => consts::csafe_commands::SET_CALORIES,
+            Concept2Response::SetProgram => consts::csafe_commands::SET_PROGRAM,
+            Concept2Response::SetUserInfo => consts::csafe_commands::SET_USER_INFO,
+            Concept2Response::SetPower => consts::csafe_commands::SET_POWER,
+            Concept2Response::GetStatus(_) => consts::csafe_commands::GET_STATUS,
+            Concept2Response::Reset => consts::csafe_commands::RESET,
+            Concept2Response::GoIdle => consts::csafe_commands::GO_IDLE,
+            Concept2Response::GoHaveID => consts::csafe_commands::GO_HAVE_ID,
+            Concept2Response::GoInUse => consts::csafe_commands::GO_IN_USE,
+            Concept2Response::GoFinished => consts::csafe_commands::GO_FINISHED,
+            Concept2Response::GoReady => consts::csafe_commands::GO_READY,
+            Concept2Response::BadID => consts::csafe_commands::BAD_ID,
+            Concept2Response::GetVersion(_) => consts::csafe_commands::GET_VERSION,
+            Concept2Response::GetUserID(_) => consts::csafe_commands::GET_USER_ID,
+            Concept2Response::GetUnits(_) => consts::csafe_commands::GET_UNITS,
+            Concept2Response::GetSerialNumber(_) => consts::csafe_commands::GET_SERIAL_NUMBER,
+            Concept2Response::GetOdometer(_) => consts::csafe_commands::GET_ODOMETER,
+            Concept2Response::GetErrorCode(_) => consts::csafe_commands::GET_ERROR_CODE,
+            Concept2Response::GetTWork(_) => consts::csafe_commands::GET_TWORK,
+            Concept2Response::GetHorizontal(_) => consts::csafe_commands::GET_HORIZONTAL,
+            Concept2Response::GetCalories(_) => consts::csafe_commands::GET_CALORIES,
+            Concept2Response::GetProgram(..) => consts::csafe_commands::GET_PROGRAM,
+            Concept2Response::GetPace(_) => consts::csafe_commands::GET_PACE,
+            Concept2Response::GetCadence(_) => consts::csafe_commands::GET_CADENCE,
+            Concept2Response::GetUserInfo(..) => consts::csafe_commands::GET_USER_INFO,
+            Concept2Response::GetHeartRate(_) => consts::csafe_commands::GET_HEART_RATE,
+            Concept2Response::GetPower(_) => consts::csafe_commands::GET_POWER,
+            Concept2Response::ProprietaryCommand(_) => consts::csafe_commands::PROPRIETARY_COMMAND,
+            Concept2Response::ProprietaryConfigCommand(_) => {
+                consts::csafe_commands::PROPRIETARY_CONFIG_COMMAND
+            }
+        }
+    }
+}
+
+impl Concept2ResponseProprietary {
+    pub fn identifier(&self) -> u8 {
+        match self {
+            Concept2ResponseProprietary::GetWorkTime(_) => consts::csafe_commands::GET_WORK_TIME,
+            Concept2ResponseProprietary::GetWorkDistance(_) => {
+                consts::csafe_commands::GET_WORK_DISTANCE
+            }
+            Concept2ResponseProprietary::GetWorkoutType(_) => {
+                consts::csafe_commands::GET_WORKOUT_TYPE
+            }
+            Concept2ResponseProprietary::GetStrokeStats(_) => {
+                consts::csafe_commands::GET_STROKE_STATS
+            }
+            Concept2ResponseProprietary::GetForcePlotData(_) => {
+                consts::csafe_commands::GET_FORCE_PLOT_DATA
+            }
+            Concept2ResponseProprietary::GetHeartRateBeltInfo(_) => {
+                consts::csafe_commands::GET_EXTENDED_HR_BELT_INFO
+            }
+            Concept2ResponseProprietary::GetDisplayType(_) => {
+                consts::csafe_commands::GET_DISPLAY_TYPE
+            }
+            Concept2ResponseProprietary::GetDisplayUnits(_) => {
+                consts::csafe_commands::GET_DISPLAY_UNITS
+            }
+            Concept2ResponseProprietary::GetWorkoutState(_) => {
+                consts::csafe_commands::GET_WORKOUT_STATE
+            }
+            Concept2ResponseProprietary::GetIntervalType(_) => {
+                consts::csafe_commands::GET_INTERVAL_TYPE
+            }
+            Concept2ResponseProprietary::GetOperationalState(_) => {
+                consts::csafe_commands::GET_OPERATIONAL_STATE
+            }
+            Concept2ResponseProprietary::GetRowingState(_) => {
+                consts::csafe_commands::GET_ROWING_STATE
+            }
+            Concept2ResponseProprietary::GetBatteryLevel(_) => {
+                consts::csafe_commands::GET_BATTERY_LEVEL
+            }
+            Concept2ResponseProprietary::GetWorkoutIntervalCount(_) => {
+                consts::csafe_commands::GET_WORKOUT_INTERVAL_COUNT
+            }
+            Concept2ResponseProprietary::GetStrokePace(_) => {
+                consts::csafe_commands::GET_STROKE_PACE
+            }
+            Concept2ResponseProprietary::GetStrokePower(_) => {
+                consts::csafe_commands::GET_STROKE_POWER
+            }
+            Concept2ResponseProprietary::GetStrokeCaloricBurnRate(_) => {
+                consts::csafe_commands::GET_STROKE_CALORIC_BURN_RATE
+            }
+            Concept2ResponseProprietary::GetSplitAvgPace(_) => {
+                consts::csafe_commands::GET_SPLIT_AVG_PACE
+            }
+            Concept2ResponseProprietary::GetSplitAvgPower(_) => {
+                consts::csafe_commands::GET_SPLIT_AVG_POWER
+            }
+            Concept2ResponseProprietary::GetTotalAvgPace(_) => {
+                consts::csafe_commands::GET_TOTAL_AVG_PACE
+            }
+            Concept2ResponseProprietary::GetTotalAvgPower(_) => {
+                consts::csafe_commands::GET_TOTAL_AVG_POWER
+            }
+            Concept2ResponseProprietary::GetTotalAvgCalories(_) => {
+                consts::csafe_commands::GET_TOTAL_AVG_CALORIES
+            }
+            Concept2ResponseProprietary::GetStrokeRate(_) => {
+                consts::csafe_commands::GET_STROKE_RATE
+            }
+            Concept2ResponseProprietary::GetSplitAvgStrokeRate(_) => {
+                consts::csafe_commands::GET_SPLIT_AVG_STROKE_RATE
+            }
+            Concept2ResponseProprietary::GetTotalAvgStrokeRate(_) => {
+                consts::csafe_commands::GET_TOTAL_AVG_STROKE_RATE
+            }
+            Concept2ResponseProprietary::GetAvgHeartRate(_) => {
+                consts::csafe_commands::GET_AVG_HEART_RATE
+            }
+            Concept2ResponseProprietary::GetLastSplitTime(_) => {
+                consts::csafe_commands::GET_LAST_SPLIT_TIME
+            }
+            Concept2ResponseProprietary::GetLastSplitDistance(_) => {
+                consts::csafe_commands::GET_LAST_SPLIT_DISTANCE
+            }
+            Concept2ResponseProprietary::GetTargetPaceTime(_) => {
+                consts::csafe_commands::GET_TARGET_PACE_TIME
+            }
+            Concept2ResponseProprietary::GetStrokeState(_) => {
+                consts::csafe_commands::GET_STROKE_STATE
+            }
+            Concept2ResponseProprietary::GetDragFactor(_) => {
+                consts::csafe_commands::GET_DRAG_FACTOR
+            }
+            Concept2ResponseProprietary::GetErrorType(_) => consts::csafe_commands::GET_ERROR_TYPE,
+            Concept2ResponseProprietary::GetErrorValue(_) => {
+                consts::csafe_commands::GET_ERROR_VALUE
+            }
+            Concept2ResponseProprietary::GetRestTime(_) => consts::csafe_commands::GET_REST_TIME,
+            Concept2ResponseProprietary::GetErgMachineType(_) => {
+                consts::csafe_commands::GET_ERG_MACHINE_TYPE
+            }
+        }
+    }
+}
+
+impl Concept2ResponseProprietaryConfig {
+    pub fn identifier(&self) -> u8 {
+        match self {
+            Concept2ResponseProprietaryConfig::SetWorkoutType => {
+                consts::csafe_commands::SET_WORKOUT_TYPE
+            }
+            Concept2ResponseProprietaryConfig::SetWorkoutDuration => {
+                consts::csafe_commands::SET_WORKOUT_DURATION
+            }
+            Concept2ResponseProprietaryConfig::SetRestDuration => {
+                consts::csafe_commands::SET_REST_DURATION
+            }
+            Concept2ResponseProprietaryConfig::SetSplitDuration => {
+                consts::csafe_commands::SET_SPLIT_DURATION
+            }
+            Concept2ResponseProprietaryConfig::SetTargetPaceTime => {
+                consts::csafe_commands::SET_TARGET_PACE_TIME
+            }
+            Concept2ResponseProprietaryConfig::SetScreenState => {
+                consts::csafe_commands::SET_SCREEN_STATE
+            }
+            Concept2ResponseProprietaryConfig::ConfigureWorkout => {
+                consts::csafe_commands::CONFIGURE_WORKOUT
+            }
+            Concept2ResponseProprietaryConfig::SetIntervalType => {
+                consts::csafe_commands::SET_INTERVAL_TYPE
+            }
+            Concept2ResponseProprietaryConfig::SetWorkoutIntervalCount => {
+                consts::csafe_commands::SET_WORKOUT_INTERVAL_COUNT
+            }
+        }
+    }
+}
+
+/// Measurements of the most recent stroke, as returned by `GetStrokeStats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrokeStats {
+    /// Hundredths of a meter.
+    pub stroke_distance: u16,
+    /// Hundredths of a second.
+    pub drive_time: u8,
+    /// Hundredths of a second.
+    pub recovery_time: u16,
+    /// Hundredths of a meter.
+    pub stroke_length: u8,
+    pub drive_count: u16,
+    /// Tenths of a pound-force.
+    pub peak_drive_force: u16,
+    /// Tenths of a pound-force.
+    pub impulse_drive_force: u16,
+    /// Tenths of a pound-force.
+    pub avg_drive_force: u16,
+    /// Tenths of a joule.
+    pub work_per_stroke: u16,
+}
+
+impl StrokeStats {
+    fn parse(v: &[u8]) -> StrokeStats {
+        StrokeStats {
+            stroke_distance: le_u16(&v[0..]),
+            drive_time: v[2],
+            recovery_time: le_u16(&v[3..]),
+            stroke_length: v[5],
+            drive_count: le_u16(&v[6..]),
+            peak_drive_force: le_u16(&v[8..]),
+            impulse_drive_force: le_u16(&v[10..]),
+            avg_drive_force: le_u16(&v[12..]),
+            work_per_stroke: le_u16(&v[14..]),
+        }
+    }
+}
+
+/// The heart rate belt that's paired with the PM, as returned by `GetHeartRateBeltInfo`.
+/// A belt ID of 0 means that no belt is paired.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeartRateBelt {
+    pub manufacturer_id: u8,
+    pub device_type: u8,
+    pub belt_id: u32,
+}
+
+impl HeartRateBelt {
+    fn parse(v: &[u8]) -> HeartRateBelt {
+        HeartRateBelt {
+            manufacturer_id: v[0],
+            device_type: v[1],
+            belt_id: le_u32(&v[2..]),
+        }
+    }
+
+    pub fn is_paired(&self) -> bool {
+        self.belt_id != 0
+    }
 }
 
 /// A struct that contains all of the parts of a *single* ResponseFrame.
 /// When the bytes come back, each command is parsed as an identifier,
 /// the number of bytes of data that are incoming, and the data. This
 /// struct wraps those things to be parsed by the `parse` method.
+/// `offset` is where the identifier sits in the frame, so that errors can
+/// point at the offending bytes.
 pub struct ResponseFrame {
     identifier: u8,
     bytes: u8,
     data: Vec<u8>,
+    offset: usize,
 }
 
 impl ResponseFrame {
-    pub fn parse(self) -> Option<Concept2Response> {
+    pub fn parse(self) -> Result<Concept2Response, ParseError> {
         match self.identifier {
+            consts::csafe_commands::AUTO_UPLOAD => self.expect_empty(Concept2Response::AutoUpload),
+            consts::csafe_commands::SET_ID_DIGITS => {
+                self.expect_empty(Concept2Response::SetIDDigits)
+            }
+            consts::csafe_commands::SET_TIME => self.expect_empty(Concept2Response::SetTime),
+            consts::csafe_commands::SET_DATE => self.expect_empty(Concept2Response::SetDate),
+            consts::csafe_commands::SET_TIMEOUT => self.expect_empty(Concept2Response::SetTimeout),
+            consts::csafe_commands::SET_TWORK => self.expect_empty(Concept2Response::SetTWork),
+            consts::csafe_commands::SET_HORIZONTAL => {
+                self.expect_empty(Concept2Response::SetHorizontal)
+            }
+            consts::csafe_commands::SET_CALORIES => {
+                self.expect_empty(Concept2Response::SetCalories)
+            }
+            consts::csafe_commands::SET_PROGRAM => self.expect_empty(Concept2Response::SetProgram),
+            consts::csafe_commands::SET_USER_INFO => {
+                self.expect_empty(Concept2Response::SetUserInfo)
+            }
+            consts::csafe_commands::SET_POWER => self.expect_empty(Concept2Response::SetPower),
+            consts::csafe_commands::GET_STATUS => self
+                .byte()
+                .map(|b| Concept2Response::GetStatus(FrameStatus::from(b))),
+            consts::csafe_commands::RESET => self.expect_empty(Concept2Response::Reset),
+            consts::csafe_commands::GO_IDLE => self.expect_empty(Concept2Response::GoIdle),
+            consts::csafe_commands::GO_HAVE_ID => self.expect_empty(Concept2Response::GoHaveID),
+            consts::csafe_commands::GO_IN_USE => self.expect_empty(Concept2Response::GoInUse),
+            consts::csafe_commands::GO_FINISHED => self.expect_empty(Concept2Response::GoFinished),
+            consts::csafe_commands::GO_READY => self.expect_empty(Concept2Response::GoReady),
+            consts::csafe_commands::BAD_ID => self.expect_empty(Concept2Response::BadID),
+            consts::csafe_commands::GET_VERSION => {
+                self.expect_bytes(7)?;
+                Ok(Concept2Response::GetVersion(VersionInfo {
+                    manufacturer_id: self.data[0],
+                    class_id: self.data[1],
+                    model: self.data[2],
+                    hardware_version: le_u16(&self.data[3..]),
+                    software_version: le_u16(&self.data[5..]),
+                }))
+            }
             consts::csafe_commands::GET_USER_ID => {
-                assert!(self.bytes == 5);
-                Some(Concept2Response::GetUserID(
-                    String::from_utf8(self.data).expect("parse error"),
-                ))
+                self.expect_bytes(5)?;
+                Ok(Concept2Response::GetUserID(self.into_string()?))
+            }
+            consts::csafe_commands::GET_UNITS => {
+                self.expect_bytes(1)?;
+                Ok(Concept2Response::GetUnits(self.data[0]))
             }
             consts::csafe_commands::GET_SERIAL_NUMBER => {
-                assert!(self.bytes == 9);
-                Some(Concept2Response::GetSerialNumber(
-                    String::from_utf8(self.data).expect("parse error"),
-                ))
+                self.expect_bytes(9)?;
+                Ok(Concept2Response::GetSerialNumber(self.into_string()?))
             }
             consts::csafe_commands::GET_ODOMETER => {
-                assert!(self.bytes == 5);
-                let distance: u32 = u32::from_le_bytes(
-                    self.data
-                        .iter()
-                        .cloned()
-                        .take(4)
-                        .collect::<Vec<u8>>()
-                        .as_slice()
-                        .try_into()
-                        .expect("incorrect slice length"),
-                );
-                let units: u8 = *self.data.last().unwrap();
-                Some(Concept2Response::GetOdometer(distance, units))
-            }
-            consts::csafe_commands::PROPRIETARY_COMMAND => parse_proprietary(self.data),
-            _ => None,
+                self.expect_bytes(5)?;
+                Ok(Concept2Response::GetOdometer(Distance::new(
+                    le_u32(&self.data[0..4]),
+                    Units::from(self.data[4]),
+                )))
+            }
+            consts::csafe_commands::GET_ERROR_CODE => {
+                self.expect_bytes(3)?;
+                Ok(Concept2Response::GetErrorCode(le_u24(&self.data)))
+            }
+            consts::csafe_commands::GET_TWORK => {
+                self.expect_bytes(3)?;
+                Ok(Concept2Response::GetTWork(units::from_hms(
+                    self.data[0],
+                    self.data[1],
+                    self.data[2],
+                )))
+            }
+            consts::csafe_commands::GET_HORIZONTAL => self
+                .measurement()
+                .map(|m| Concept2Response::GetHorizontal(Distance::new(m.value, m.units))),
+            consts::csafe_commands::GET_CALORIES => {
+                self.expect_bytes(2)?;
+                Ok(Concept2Response::GetCalories(le_u16(&self.data)))
+            }
+            consts::csafe_commands::GET_PROGRAM => {
+                self.expect_bytes(2)?;
+                Ok(Concept2Response::GetProgram(self.data[0], self.data[1]))
+            }
+            consts::csafe_commands::GET_PACE => self.measurement().map(Concept2Response::GetPace),
+            consts::csafe_commands::GET_CADENCE => {
+                self.measurement().map(Concept2Response::GetCadence)
+            }
+            consts::csafe_commands::GET_USER_INFO => {
+                self.expect_bytes(5)?;
+                Ok(Concept2Response::GetUserInfo(
+                    Measurement::new(u32::from(le_u16(&self.data)), Units::from(self.data[2])),
+                    self.data[3],
+                    self.data[4],
+                ))
+            }
+            consts::csafe_commands::GET_HEART_RATE => {
+                self.expect_bytes(1)?;
+                Ok(Concept2Response::GetHeartRate(self.data[0]))
+            }
+            consts::csafe_commands::GET_POWER => self.measurement().map(Concept2Response::GetPower),
+            consts::csafe_commands::PROPRIETARY_COMMAND => {
+                parse_proprietary(&self.data, self.data_offset())
+            }
+            consts::csafe_commands::PROPRIETARY_CONFIG_COMMAND => {
+                split_frames(&self.data, self.data_offset())?
+                    .into_iter()
+                    .map(ResponseFrame::parse_proprietary_config)
+                    .collect::<Result<Vec<Concept2ResponseProprietaryConfig>, ParseError>>()
+                    .map(Concept2Response::ProprietaryConfigCommand)
+            }
+            identifier => Err(ParseError::UnknownIdentifier {
+                offset: self.offset,
+                identifier,
+            }),
         }
     }
-}
 
-/// Because these proprietary responses are nested inside a regular response,
-/// we need an additional function to parse them.
-fn parse_proprietary(vec: Vec<u8>) -> Option<Concept2Response> {
-    let mut proprietary_vec: Vec<Concept2ResponseProprietary> = Vec::new();
-    let mut vec_iter = vec.into_iter();
-    while let Some(identifier) = vec_iter.next() {
-        match identifier {
+    /// Parses a response that was nested inside of a `PROPRIETARY_COMMAND` response.
+    /// Identifiers overlap with the public CSAFE ones, so they need their own match.
+    fn parse_proprietary(self) -> Result<Concept2ResponseProprietary, ParseError> {
+        match self.identifier {
+            // The fifth byte of these two is a fraction of the last digit, which is
+            // finer than anything the PM displays.
             consts::csafe_commands::GET_WORK_TIME => {
-                if vec_iter.next() != Some(5) {
-                    return None;
-                }
-                proprietary_vec.push(Concept2ResponseProprietary::GetWorkTime(
-                    u32::from_le_bytes(
-                        vec_iter
-                            .by_ref()
-                            .take(4)
-                            .collect::<Vec<u8>>()
-                            .as_slice()
-                            .try_into()
-                            .expect("incorrect slice length"),
-                    ),
-                    vec_iter.next().unwrap(),
-                ));
+                self.expect_bytes(5)?;
+                Ok(Concept2ResponseProprietary::GetWorkTime(
+                    units::from_hundredths(le_u32(&self.data[0..4])),
+                ))
             }
             consts::csafe_commands::GET_WORK_DISTANCE => {
-                if vec_iter.next() != Some(5) {
-                    return None;
-                }
-                proprietary_vec.push(Concept2ResponseProprietary::GetWorkDistance(
-                    u32::from_le_bytes(
-                        vec_iter
-                            .by_ref()
-                            .take(4)
-                            .collect::<Vec<u8>>()
-                            .as_slice()
-                            .try_into()
-                            .expect("incorrect slice length"),
-                    ),
-                    vec_iter.next().unwrap(),
-                ));
+                self.expect_bytes(5)?;
+                Ok(Concept2ResponseProprietary::GetWorkDistance(Distance::new(
+                    le_u32(&self.data[0..4]),
+                    Units::TenthMeter,
+                )))
             }
             consts::csafe_commands::GET_WORKOUT_TYPE => {
-                if vec_iter.next() != Some(1) {
-                    return None;
+                self.expect_bytes(1)?;
+                Ok(Concept2ResponseProprietary::GetWorkoutType(
+                    WorkoutType::from(self.data[0]),
+                ))
+            }
+            consts::csafe_commands::GET_STROKE_STATS => {
+                self.expect_bytes(16)?;
+                Ok(Concept2ResponseProprietary::GetStrokeStats(
+                    StrokeStats::parse(&self.data),
+                ))
+            }
+            consts::csafe_commands::GET_EXTENDED_HR_BELT_INFO => {
+                self.expect_bytes(6)?;
+                Ok(Concept2ResponseProprietary::GetHeartRateBeltInfo(
+                    HeartRateBelt::parse(&self.data),
+                ))
+            }
+            // A count of the bytes that were read, then that many bytes of 16-bit
+            // samples. The PM pads the rest of the block with zeros.
+            consts::csafe_commands::GET_FORCE_PLOT_DATA => {
+                let read = self.data.first().copied().unwrap_or(0);
+                if read >= self.bytes {
+                    return Err(ParseError::BadLength {
+                        offset: self.offset + 1,
+                        identifier: self.identifier,
+                        expected: read.saturating_add(1),
+                        actual: self.bytes,
+                    });
                 }
-                proprietary_vec.push(Concept2ResponseProprietary::GetWorkoutType(
-                    vec_iter.next().unwrap(),
+                // Half a sample means the count is wrong; expect the whole last sample.
+                if read % 2 != 0 {
+                    return Err(ParseError::BadLength {
+                        offset: self.offset + 1,
+                        identifier: self.identifier,
+                        expected: read.saturating_add(2),
+                        actual: self.bytes,
+                    });
+                }
+                Ok(Concept2ResponseProprietary::GetForcePlotData(
+                    self.data[1..=usize::from(read)]
+                        .chunks_exact(2)
+                        .map(le_u16)
+                        .collect(),
                 ))
             }
-            _ => {
-                return None;
+            consts::csafe_commands::GET_DISPLAY_TYPE => {
+                self.byte().map(Concept2ResponseProprietary::GetDisplayType)
+            }
+            consts::csafe_commands::GET_DISPLAY_UNITS => self
+                .byte()
+                .map(Concept2ResponseProprietary::GetDisplayUnits),
+            consts::csafe_commands::GET_WORKOUT_STATE => self
+                .byte()
+                .map(|b| Concept2ResponseProprietary::GetWorkoutState(b.into())),
+            consts::csafe_commands::GET_INTERVAL_TYPE => self
+                .byte()
+                .map(|b| Concept2ResponseProprietary::GetIntervalType(b.into())),
+            consts::csafe_commands::GET_OPERATIONAL_STATE => self
+                .byte()
+                .map(|b| Concept2ResponseProprietary::GetOperationalState(b.into())),
+            consts::csafe_commands::GET_ROWING_STATE => self
+                .byte()
+                .map(|b| Concept2ResponseProprietary::GetRowingState(b.into())),
+            consts::csafe_commands::GET_BATTERY_LEVEL => self
+                .byte()
+                .map(Concept2ResponseProprietary::GetBatteryLevel),
+            consts::csafe_commands::GET_WORKOUT_INTERVAL_COUNT => self
+                .byte()
+                .map(Concept2ResponseProprietary::GetWorkoutIntervalCount),
+            consts::csafe_commands::GET_STROKE_PACE => self
+                .hundredths()
+                .map(Concept2ResponseProprietary::GetStrokePace),
+            consts::csafe_commands::GET_STROKE_POWER => {
+                self.u32().map(Concept2ResponseProprietary::GetStrokePower)
+            }
+            consts::csafe_commands::GET_STROKE_CALORIC_BURN_RATE => self
+                .u32()
+                .map(Concept2ResponseProprietary::GetStrokeCaloricBurnRate),
+            consts::csafe_commands::GET_SPLIT_AVG_PACE => self
+                .hundredths()
+                .map(Concept2ResponseProprietary::GetSplitAvgPace),
+            consts::csafe_commands::GET_SPLIT_AVG_POWER => self
+                .u32()
+                .map(Concept2ResponseProprietary::GetSplitAvgPower),
+            consts::csafe_commands::GET_TOTAL_AVG_PACE => self
+                .hundredths()
+                .map(Concept2ResponseProprietary::GetTotalAvgPace),
+            consts::csafe_commands::GET_TOTAL_AVG_POWER => self
+                .u32()
+                .map(Concept2ResponseProprietary::GetTotalAvgPower),
+            consts::csafe_commands::GET_TOTAL_AVG_CALORIES => self
+                .u32()
+                .map(Concept2ResponseProprietary::GetTotalAvgCalories),
+            consts::csafe_commands::GET_STROKE_RATE => {
+                self.byte().map(Concept2ResponseProprietary::GetStrokeRate)
+            }
+            consts::csafe_commands::GET_SPLIT_AVG_STROKE_RATE => self
+                .byte()
+                .map(Concept2ResponseProprietary::GetSplitAvgStrokeRate),
+            consts::csafe_commands::GET_TOTAL_AVG_STROKE_RATE => self
+                .byte()
+                .map(Concept2ResponseProprietary::GetTotalAvgStrokeRate),
+            consts::csafe_commands::GET_AVG_HEART_RATE => self
+                .byte()
+                .map(Concept2ResponseProprietary::GetAvgHeartRate),
+            consts::csafe_commands::GET_LAST_SPLIT_TIME => self
+                .hundredths()
+                .map(Concept2ResponseProprietary::GetLastSplitTime),
+            consts::csafe_commands::GET_LAST_SPLIT_DISTANCE => self.u32().map(|m| {
+                Concept2ResponseProprietary::GetLastSplitDistance(Distance::from_meters(m))
+            }),
+            consts::csafe_commands::GET_TARGET_PACE_TIME => self
+                .hundredths()
+                .map(Concept2ResponseProprietary::GetTargetPaceTime),
+            consts::csafe_commands::GET_STROKE_STATE => self
+                .byte()
+                .map(|b| Concept2ResponseProprietary::GetStrokeState(b.into())),
+            consts::csafe_commands::GET_DRAG_FACTOR => {
+                self.byte().map(Concept2ResponseProprietary::GetDragFactor)
             }
+            consts::csafe_commands::GET_ERROR_TYPE => {
+                self.byte().map(Concept2ResponseProprietary::GetErrorType)
+            }
+            consts::csafe_commands::GET_ERROR_VALUE => {
+                self.u16().map(Concept2ResponseProprietary::GetErrorValue)
+            }
+            consts::csafe_commands::GET_REST_TIME => self.u16().map(|secs| {
+                Concept2ResponseProprietary::GetRestTime(Duration::from_secs(u64::from(secs)))
+            }),
+            consts::csafe_commands::GET_ERG_MACHINE_TYPE => self
+                .byte()
+                .map(|b| Concept2ResponseProprietary::GetErgMachineType(b.into())),
+            identifier => Err(ParseError::UnknownIdentifier {
+                offset: self.offset,
+                identifier,
+            }),
         }
     }
-    Some(Concept2Response::ProprietaryCommand(proprietary_vec))
-}
 
-/// Takes an iterator of bytes, takes a single chunk of bytes according to the
-/// `bytes` field of the frame, and constructs a `ResponseFrame` struct to pass
-/// to the `parse` method.
-fn parse_c2r<'a, T>(iter: &mut T) -> Option<Concept2Response>
-where
-    T: Iterator<Item = &'a u8>,
-{
-    let identifier = iter.next();
-    let bytes = iter.next();
-    match (identifier, bytes) {
-        (Some(i), Some(b)) => {
-            let data: Vec<u8> = iter.take(usize::from(*b)).copied().collect();
-            if data.len() == usize::from(*b) {
-                ResponseFrame {
-                    identifier: *i,
-                    bytes: *b,
-                    data,
-                }
-                .parse()
-            } else {
-                None
+    /// Parses a response that was nested inside of a `PROPRIETARY_CONFIG_COMMAND` response.
+    fn parse_proprietary_config(self) -> Result<Concept2ResponseProprietaryConfig, ParseError> {
+        match self.identifier {
+            consts::csafe_commands::SET_WORKOUT_TYPE => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetWorkoutType)
+            }
+            consts::csafe_commands::SET_WORKOUT_DURATION => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetWorkoutDuration)
+            }
+            consts::csafe_commands::SET_REST_DURATION => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetRestDuration)
+            }
+            consts::csafe_commands::SET_SPLIT_DURATION => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetSplitDuration)
             }
+            consts::csafe_commands::SET_TARGET_PACE_TIME => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetTargetPaceTime)
+            }
+            consts::csafe_commands::SET_SCREEN_STATE => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetScreenState)
+            }
+            consts::csafe_commands::CONFIGURE_WORKOUT => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::ConfigureWorkout)
+            }
+            consts::csafe_commands::SET_INTERVAL_TYPE => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetIntervalType)
+            }
+            consts::csafe_commands::SET_WORKOUT_INTERVAL_COUNT => {
+                self.expect_empty(Concept2ResponseProprietaryConfig::SetWorkoutIntervalCount)
+            }
+            identifier => Err(ParseError::UnknownIdentifier {
+                offset: self.offset,
+                identifier,
+            }),
         }
-        _ => None,
     }
+
+    /// Offset of the first data byte, right after the identifier and the byte count.
+    fn data_offset(&self) -> usize {
+        self.offset + 2
+    }
+
+    fn expect_bytes(&self, expected: u8) -> Result<(), ParseError> {
+        if self.bytes == expected {
+            Ok(())
+        } else {
+            Err(ParseError::BadLength {
+                offset: self.offset + 1,
+                identifier: self.identifier,
+                expected,
+                actual: self.bytes,
+            })
+        }
+    }
+
+    /// Shorthands for responses whose data is a single number.
+    fn byte(&self) -> Result<u8, ParseError> {
+        self.expect_bytes(1).map(|_| self.data[0])
+    }
+
+    fn u16(&self) -> Result<u16, ParseError> {
+        self.expect_bytes(2).map(|_| le_u16(&self.data))
+    }
+
+    fn u32(&self) -> Result<u32, ParseError> {
+        self.expect_bytes(4).map(|_| le_u32(&self.data))
+    }
+
+    fn hundredths(&self) -> Result<Duration, ParseError> {
+        self.u32().map(units::from_hundredths)
+    }
+
+    /// A 16-bit value followed by a units byte.
+    fn measurement(&self) -> Result<Measurement, ParseError> {
+        self.expect_bytes(3)
+            .map(|_| Measurement::new(u32::from(le_u16(&self.data)), Units::from(self.data[2])))
+    }
+
+    /// For commands that don't return any data - all we get back is the identifier.
+    fn expect_empty<T>(&self, response: T) -> Result<T, ParseError> {
+        self.expect_bytes(0).map(|_| response)
+    }
+
+    fn into_string(self) -> Result<String, ParseError> {
+        let offset = self.data_offset();
+        String::from_utf8(self.data).map_err(|source| ParseError::InvalidUtf8 {
+            offset: offset + source.utf8_error().valid_up_to(),
+            source,
+        })
+    }
+}
+
+/// Reads little-endian integers out of the front of a slice. Callers have already
+/// checked the length with `expect_bytes`.
+fn le_u16(v: &[u8]) -> u16 {
+    u16::from_le_bytes([v[0], v[1]])
 }
 
-/// Applies `parse_c2r` on an iterator of bytes until no more `Concept2Response` structs can
-/// be constructed from the bytes.
-fn parse_helper<'a>(iter: &mut impl Iterator<Item = &'a u8>) -> Option<Vec<Concept2Response>> {
+fn le_u24(v: &[u8]) -> u32 {
+    u32::from_le_bytes([v[0], v[1], v[2], 0])
+}
+
+fn le_u32(v: &[u8]) -> u32 {
+    u32::from_le_bytes([v[0], v[1], v[2], v[3]])
+}
+
+/// Because these proprietary responses are nested inside a regular response,
+/// we need an additional function to parse them.
+fn parse_proprietary(v: &[u8], offset: usize) -> Result<Concept2Response, ParseError> {
+    split_frames(v, offset)?
+        .into_iter()
+        .map(ResponseFrame::parse_proprietary)
+        .collect::<Result<Vec<Concept2ResponseProprietary>, ParseError>>()
+        .map(Concept2Response::ProprietaryCommand)
+}
+
+/// Splits a slice of bytes into `ResponseFrame` structs, each one taking a single chunk
+/// of bytes according to its `bytes` field. `offset` is the position of `v[0]` in the frame.
+fn split_frames(v: &[u8], offset: usize) -> Result<Vec<ResponseFrame>, ParseError> {
     let mut result = vec![];
-    while let Some(c2r) = parse_c2r(iter) {
-        result.push(c2r);
+    let mut i = 0;
+    while i < v.len() {
+        let bytes = *v.get(i + 1).ok_or(ParseError::Truncated {
+            offset: offset + i + 1,
+            needed: 1,
+            available: 0,
+        })?;
+        let start = i + 2;
+        let end = start + usize::from(bytes);
+        let data = v.get(start..end).ok_or(ParseError::Truncated {
+            offset: offset + start,
+            needed: usize::from(bytes),
+            available: v.len() - start.min(v.len()),
+        })?;
+        result.push(ResponseFrame {
+            identifier: v[i],
+            bytes,
+            data: data.to_vec(),
+            offset: offset + i,
+        });
+        i = end;
     }
-    Some(result)
+    Ok(result)
+}
+
+/// Applies `ResponseFrame::parse` on each chunk of the bytes until no more
+/// `Concept2Response` structs can be constructed from the bytes.
+fn parse_helper(v: &[u8], offset: usize) -> Result<Vec<Concept2Response>, ParseError> {
+    split_frames(v, offset)?
+        .into_iter()
+        .map(ResponseFrame::parse)
+        .collect()
 }
 
 fn checksum_iter<'a>(iter: impl Iterator<Item = &'a u8>) -> u8 {
@@ -175,50 +833,102 @@ fn checksum_iter<'a>(iter: impl Iterator<Item = &'a u8>) -> u8 {
 /// bytes. As a result, the data fields cannot contain these bytes. So, the Concept2
 /// machine replaces data bytes with these values with `0xf3`, followed by a number.
 /// This function replaces these "stuffed" pairs of bytes with the actual data value.
-fn unpack_bytes(v: &[u8]) -> Vec<u8> {
+/// `v` starts at the start flag, which is copied as-is; everything after the stop flag
+/// (HID padding, usually) is dropped.
+fn unpack_bytes(v: &[u8], offset: usize) -> Result<Vec<u8>, ParseError> {
     let mut vec_iter = v.iter();
-    // Skipping the report number and the start flag.
-    let mut result: Vec<u8> = vec_iter.by_ref().take(2).cloned().collect();
+    let mut result: Vec<u8> = vec_iter.by_ref().take(1).cloned().collect();
     while let Some(x) = vec_iter.next() {
         match x {
-            0xf2 => {
-                result.push(0xf2);
-                return result;
+            &consts::CSAFE_STOP_FLAG => {
+                result.push(consts::CSAFE_STOP_FLAG);
+                return Ok(result);
             }
             0xf3 => match vec_iter.next() {
                 Some(&0x00) => result.push(0xf0),
                 Some(&0x01) => result.push(0xf1),
                 Some(&0x02) => result.push(0xf2),
                 Some(&0x03) => result.push(0xf3),
-                _ => {
-                    result.push(0xf3);
-                    return result;
+                found => {
+                    return Err(ParseError::InvalidStuffing {
+                        offset: offset + result.len(),
+                        found: found.copied(),
+                    })
                 }
             },
             x => result.push(*x),
         }
     }
-    result
+    Err(ParseError::BadStopFlag {
+        offset: offset + result.len(),
+        found: None,
+    })
 }
 
-/// Single public method for taking a vector of bytes and
-/// returning a vector of `Concept2Response` frames.
-pub fn parse_vec(v: &[u8]) -> Option<Vec<Concept2Response>> {
-    let unpacked_vec: Vec<u8> = unpack_bytes(v);
-    let start_flag = unpacked_vec.get(1);
-    let end_flag = unpacked_vec.iter().rev().next();
-    let checksum = unpacked_vec.iter().rev().nth(1);
-    let length = unpacked_vec.len();
-    let actual_checksum = checksum_iter(unpacked_vec.iter().skip(2).take(length - 4));
-    match (
-        start_flag,
-        end_flag,
-        checksum.map(|x| *x == actual_checksum),
-    ) {
-        (Some(&consts::CSAFE_START_FLAG), Some(&consts::CSAFE_STOP_FLAG), Some(true)) => {
-            parse_helper(&mut v.iter().skip(3).take(length - 5))
+/// Validates a single frame - start flag, addresses for Extended frames, status byte, data,
+/// checksum, stop flag - and parses its contents. `offset` is the position of the start flag
+/// in the caller's input.
+pub(crate) fn parse_frame_at(v: &[u8], offset: usize) -> Result<CSAFEResponseFrame, ParseError> {
+    // Everything before the status byte.
+    let header = match v.first() {
+        Some(&consts::CSAFE_START_FLAG) => 1,
+        Some(&consts::CSAFE_EXTENDED_START_FLAG) => 3,
+        found => {
+            return Err(ParseError::BadStartFlag {
+                offset,
+                found: found.copied(),
+            })
         }
-        _ => None,
+    };
+    let unpacked_vec: Vec<u8> = unpack_bytes(v, offset)?;
+    let length = unpacked_vec.len();
+    // The header, status byte, checksum, and stop flag are the bare minimum.
+    if length < header + 3 {
+        return Err(ParseError::Truncated {
+            offset: offset + length - 1,
+            needed: header + 3,
+            available: length,
+        });
+    }
+    let expected = unpacked_vec[length - 2];
+    let actual = checksum_iter(unpacked_vec[header..length - 2].iter());
+    if expected != actual {
+        return Err(ParseError::ChecksumMismatch {
+            offset: offset + length - 2,
+            expected,
+            actual,
+        });
+    }
+    Ok(CSAFEResponseFrame {
+        address: if header == 3 {
+            Some(FrameAddress {
+                destination: unpacked_vec[1],
+                source: unpacked_vec[2],
+            })
+        } else {
+            None
+        },
+        status: FrameStatus::from(unpacked_vec[header]),
+        responses: parse_helper(&unpacked_vec[header + 1..length - 2], offset + header + 1)?,
+    })
+}
+
+/// Takes a vector of bytes and returns a vector of `Concept2Response` frames,
+/// discarding the frame's status byte.
+/// The first byte is the HID report number, followed by the frame itself.
+pub fn parse_vec(v: &[u8]) -> Result<Vec<Concept2Response>, ParseError> {
+    parse_frame(v).map(|frame| frame.responses)
+}
+
+/// Like `parse_vec`, but keeps the frame's status byte.
+pub fn parse_frame(v: &[u8]) -> Result<CSAFEResponseFrame, ParseError> {
+    match v.split_first() {
+        Some((_, frame)) => parse_frame_at(frame, 1),
+        None => Err(ParseError::Truncated {
+            offset: 0,
+            needed: 1,
+            available: 0,
+        }),
     }
 }
 
@@ -229,7 +939,7 @@ mod tests {
             0x1, 0xf1, 0x81, 0x92, 0x5, 0x30, 0x30, 0x30, 0x30, 0x30, 0x26, 0xf2,
         ];
         assert_eq!(
-            Some(vec![super::Concept2Response::GetUserID(String::from(
+            Ok(vec![super::Concept2Response::GetUserID(String::from(
                 "00000"
             ))]),
             super::parse_vec(&v)
@@ -243,7 +953,7 @@ mod tests {
             0xf2,
         ];
         assert_eq!(
-            Some(vec![super::Concept2Response::GetSerialNumber(
+            Ok(vec![super::Concept2Response::GetSerialNumber(
                 String::from("430228525")
             )]),
             super::parse_vec(&v)
@@ -256,7 +966,9 @@ mod tests {
             0x1, 0xf1, 0x81, 0x9b, 0x5, 0xf4, 0x24, 0x21, 0x0, 0x24, 0xca, 0xf2,
         ];
         assert_eq!(
-            Some(vec![super::Concept2Response::GetOdometer(2172148, 0x24)]),
+            Ok(vec![super::Concept2Response::GetOdometer(
+                crate::units::Distance::new(2172148, crate::units::Units::Meter)
+            )]),
             super::parse_vec(&v)
         );
     }
@@ -267,8 +979,11 @@ mod tests {
             0x1, 0xf1, 0x1, 0x1a, 0x7, 0xa3, 0x5, 0x0, 0x0, 0x0, 0x0, 0x0, 0xba, 0xf2,
         ];
         assert_eq!(
-            Some(vec![super::Concept2Response::ProprietaryCommand(vec![
-                super::Concept2ResponseProprietary::GetWorkDistance(0, 0)
+            Ok(vec![super::Concept2Response::ProprietaryCommand(vec![
+                super::Concept2ResponseProprietary::GetWorkDistance(crate::units::Distance::new(
+                    0,
+                    crate::units::Units::TenthMeter
+                ))
             ])]),
             super::parse_vec(&v)
         );
@@ -281,9 +996,14 @@ mod tests {
             0xf2,
         ];
         assert_eq!(
-            Some(vec![super::Concept2Response::ProprietaryCommand(vec![
-                super::Concept2ResponseProprietary::GetWorkDistance(0, 0),
-                super::Concept2ResponseProprietary::GetWorkoutType(8)
+            Ok(vec![super::Concept2Response::ProprietaryCommand(vec![
+                super::Concept2ResponseProprietary::GetWorkDistance(crate::units::Distance::new(
+                    0,
+                    crate::units::Units::TenthMeter
+                )),
+                super::Concept2ResponseProprietary::GetWorkoutType(
+                    crate::workout::WorkoutType::VariableInterval
+                )
             ])]),
             super::parse_vec(&v)
         );
@@ -297,15 +1017,266 @@ mod tests {
             0x32, 0x35, 0x29, 0xf2,
         ];
         assert_eq!(
-            Some(vec![
+            Ok(vec![
                 super::Concept2Response::ProprietaryCommand(vec![
-                    super::Concept2ResponseProprietary::GetWorkTime(0, 0),
-                    super::Concept2ResponseProprietary::GetWorkDistance(0, 0),
-                    super::Concept2ResponseProprietary::GetWorkoutType(8)
+                    super::Concept2ResponseProprietary::GetWorkTime(
+                        crate::units::Duration::from_secs(0)
+                    ),
+                    super::Concept2ResponseProprietary::GetWorkDistance(
+                        crate::units::Distance::new(0, crate::units::Units::TenthMeter)
+                    ),
+                    super::Concept2ResponseProprietary::GetWorkoutType(
+                        crate::workout::WorkoutType::VariableInterval
+                    )
                 ]),
                 super::Concept2Response::GetSerialNumber(String::from("430228525"))
             ]),
             super::parse_vec(&v)
         );
     }
+
+    #[test]
+    fn test_parse_public_short_commands() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0xa6, 0x3, 0x69, 0x0, 0x39, 0xa7, 0x3, 0x1c, 0x0, 0x54, 0xb0, 0x1,
+            0x8f, 0xb4, 0x3, 0xc8, 0x0, 0x58, 0xa0, 0x3, 0x0, 0x1e, 0x2d, 0x11, 0xf2,
+        ];
+        assert_eq!(
+            Ok(vec![
+                super::Concept2Response::GetPace(crate::units::Measurement::new(
+                    105,
+                    crate::units::Units::SecondsPerKilometer
+                )),
+                super::Concept2Response::GetCadence(crate::units::Measurement::new(
+                    28,
+                    crate::units::Units::StrokesPerMinute
+                )),
+                super::Concept2Response::GetHeartRate(143),
+                super::Concept2Response::GetPower(crate::units::Measurement::new(
+                    200,
+                    crate::units::Units::Watts
+                )),
+                super::Concept2Response::GetTWork(crate::units::Duration::from_secs(1845)),
+            ]),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_response() {
+        let v: Vec<u8> = vec![0x1, 0xf1, 0x81, 0x85, 0x0, 0x4, 0xf2];
+        assert_eq!(
+            Ok(vec![super::Concept2Response::GoInUse]),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_proprietary_state_and_stroke_stats() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0x1a, 0x1b, 0x8d, 0x1, 0x1, 0xbf, 0x1, 0x2, 0xc1, 0x1, 0x7d, 0x6e,
+            0x10, 0x9a, 0x3, 0x50, 0xa0, 0x0, 0x96, 0xc, 0x0, 0x2c, 0x7, 0xae, 0x4, 0x58, 0x4, 0x4,
+            0x11, 0x49, 0xf2,
+        ];
+        assert_eq!(
+            Ok(vec![super::Concept2Response::ProprietaryCommand(vec![
+                super::Concept2ResponseProprietary::GetWorkoutState(
+                    crate::workout::WorkoutState::WorkoutRow
+                ),
+                super::Concept2ResponseProprietary::GetStrokeState(
+                    crate::workout::StrokeState::Driving
+                ),
+                super::Concept2ResponseProprietary::GetDragFactor(125),
+                super::Concept2ResponseProprietary::GetStrokeStats(super::StrokeStats {
+                    stroke_distance: 922,
+                    drive_time: 80,
+                    recovery_time: 160,
+                    stroke_length: 150,
+                    drive_count: 12,
+                    peak_drive_force: 1836,
+                    impulse_drive_force: 1198,
+                    avg_drive_force: 1112,
+                    work_per_stroke: 4356,
+                }),
+            ])]),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_proprietary_config() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0x76, 0x6, 0x1, 0x0, 0x3, 0x0, 0x14, 0x0, 0xe7, 0xf2,
+        ];
+        assert_eq!(
+            Ok(vec![super::Concept2Response::ProprietaryConfigCommand(
+                vec![
+                    super::Concept2ResponseProprietaryConfig::SetWorkoutType,
+                    super::Concept2ResponseProprietaryConfig::SetWorkoutDuration,
+                    super::Concept2ResponseProprietaryConfig::ConfigureWorkout,
+                ]
+            )]),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_frame_status() {
+        let v: Vec<u8> = vec![0x1, 0xf1, 0x5, 0x80, 0x1, 0x5, 0x81, 0xf2];
+        assert_eq!(
+            Ok(super::CSAFEResponseFrame {
+                address: None,
+                status: crate::status::FrameStatus {
+                    frame_toggle: false,
+                    previous_frame: crate::status::PreviousFrameStatus::Ok,
+                    state: crate::status::SlaveState::InUse,
+                },
+                responses: vec![super::Concept2Response::GetStatus(
+                    crate::status::FrameStatus::from(0x5)
+                )],
+            }),
+            super::parse_frame(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_extended_frame() {
+        let v: Vec<u8> = vec![0x1, 0xf0, 0x0, 0xfd, 0x81, 0x80, 0x1, 0x81, 0x81, 0xf2];
+        let frame = super::parse_frame(&v).unwrap();
+        assert_eq!(
+            Some(crate::csafe::FrameAddress {
+                destination: crate::consts::CSAFE_HOST_ADDRESS,
+                source: crate::consts::CSAFE_DEFAULT_SECONDARY_ADDRESS,
+            }),
+            frame.address
+        );
+        assert_eq!(
+            vec![super::Concept2Response::GetStatus(
+                crate::status::FrameStatus::from(0x81)
+            )],
+            frame.responses
+        );
+    }
+
+    #[test]
+    fn test_parse_get_version() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0x91, 0x7, 0x16, 0x2, 0x5, 0x7a, 0x2, 0xd2, 0x0, 0xac, 0xf2,
+        ];
+        assert_eq!(
+            Ok(vec![super::Concept2Response::GetVersion(
+                crate::version::VersionInfo {
+                    manufacturer_id: 22,
+                    class_id: 2,
+                    model: 5,
+                    hardware_version: 634,
+                    software_version: 210,
+                }
+            )]),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_bad_checksum() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0x92, 0x5, 0x30, 0x30, 0x30, 0x30, 0x30, 0x27, 0xf2,
+        ];
+        assert_eq!(
+            Err(crate::error::ParseError::ChecksumMismatch {
+                offset: 10,
+                expected: 0x27,
+                actual: 0x26
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_bad_start_flag() {
+        let v: Vec<u8> = vec![0x1, 0x00, 0x81, 0x81, 0xf2];
+        assert_eq!(
+            Err(crate::error::ParseError::BadStartFlag {
+                offset: 1,
+                found: Some(0x00)
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_stop_flag() {
+        let v: Vec<u8> = vec![0x1, 0xf1, 0x81, 0x92, 0x5, 0x30, 0x30];
+        assert_eq!(
+            Err(crate::error::ParseError::BadStopFlag {
+                offset: 7,
+                found: None
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_response() {
+        // Byte count says 5, but only 2 bytes of data made it into the frame.
+        let v: Vec<u8> = vec![0x1, 0xf1, 0x81, 0x92, 0x5, 0x30, 0x30, 0x16, 0xf2];
+        assert_eq!(
+            Err(crate::error::ParseError::Truncated {
+                offset: 5,
+                needed: 5,
+                available: 2
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_bad_length() {
+        let v: Vec<u8> = vec![0x1, 0xf1, 0x81, 0x9b, 0x1, 0x24, 0x3f, 0xf2];
+        assert_eq!(
+            Err(crate::error::ParseError::BadLength {
+                offset: 4,
+                identifier: 0x9b,
+                expected: 5,
+                actual: 1
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_odd_force_plot_count() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0x1a, 0x6, 0x6b, 0x4, 0x3, 0x10, 0x0, 0x20, 0xc1, 0xf2,
+        ];
+        assert_eq!(
+            Err(crate::error::ParseError::BadLength {
+                offset: 6,
+                identifier: 0x6b,
+                expected: 5,
+                actual: 4
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_identifier() {
+        let v: Vec<u8> = vec![0x1, 0xf1, 0x81, 0x7f, 0x0, 0xfe, 0xf2];
+        assert_eq!(
+            Err(crate::error::ParseError::UnknownIdentifier {
+                offset: 3,
+                identifier: 0x7f
+            }),
+            super::parse_vec(&v)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_utf8() {
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x81, 0x92, 0x5, 0x30, 0x30, 0xff, 0x30, 0x30, 0xe9, 0xf2,
+        ];
+        assert_eq!(7, super::parse_vec(&v).unwrap_err().offset());
+    }
 }
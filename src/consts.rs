@@ -1,25 +1,122 @@
-/// Various byte constants to give commands names rather than working with just
-/// the bytes themselves.
+//! Various byte constants to give commands names rather than working with just
+//! the bytes themselves.
 
 /// Product ID for HID reading.
 pub const CONCEPT2_PRODUCT_ID: u16 = 0x04f3;
+pub const PM3_PRODUCT_ID: u16 = 0x0001;
+pub const PM4_PRODUCT_ID: u16 = 0x0002;
 pub const CONCEPT2_VENDOR_ID: u16 = 0x17a4;
 
 /// Start Flag for Standard Frames.
 pub const CSAFE_START_FLAG: u8 = 0xf1;
 
+/// Start Flag for Extended Frames, which carry a destination and source address.
+pub const CSAFE_EXTENDED_START_FLAG: u8 = 0xf0;
+
+/// Address of the host - that's us.
+pub const CSAFE_HOST_ADDRESS: u8 = 0x00;
+
+/// Address that a PM answers to unless it has been given another.
+pub const CSAFE_DEFAULT_SECONDARY_ADDRESS: u8 = 0xfd;
+
+/// Address that every machine on the bus answers to.
+pub const CSAFE_BROADCAST_ADDRESS: u8 = 0xff;
+
 /// Stop Flag for Frame.
 pub const CSAFE_STOP_FLAG: u8 = 0xf2;
 
 /// CSAFE identifiers for both commands and responses.
 pub mod csafe_commands {
+    /// Wrapper for the Concept2-specific commands. Identifiers nested inside of it
+    /// overlap with the public ones below.
     pub const PROPRIETARY_COMMAND: u8 = 0x1a;
+    /// Wrapper for the PM-specific configuration commands, which have their own
+    /// identifiers below.
+    pub const PROPRIETARY_CONFIG_COMMAND: u8 = 0x76;
+
+    // Public long commands. These carry data, preceded by a byte count.
+    pub const AUTO_UPLOAD: u8 = 0x01;
+    pub const SET_ID_DIGITS: u8 = 0x10;
+    pub const SET_TIME: u8 = 0x11;
+    pub const SET_DATE: u8 = 0x12;
+    pub const SET_TIMEOUT: u8 = 0x13;
+    pub const SET_TWORK: u8 = 0x20;
+    pub const SET_HORIZONTAL: u8 = 0x21;
+    pub const SET_CALORIES: u8 = 0x23;
+    pub const SET_PROGRAM: u8 = 0x24;
+    pub const SET_USER_INFO: u8 = 0x2b;
+    pub const SET_POWER: u8 = 0x34;
+
+    // Public short commands.
     pub const GET_STATUS: u8 = 0x80;
-    pub const GET_WORKOUT_TYPE: u8 = 0x89;
+    pub const RESET: u8 = 0x81;
+    pub const GO_IDLE: u8 = 0x82;
+    pub const GO_HAVE_ID: u8 = 0x83;
+    pub const GO_IN_USE: u8 = 0x85;
+    pub const GO_FINISHED: u8 = 0x86;
+    pub const GO_READY: u8 = 0x87;
+    pub const BAD_ID: u8 = 0x88;
     pub const GET_VERSION: u8 = 0x91;
     pub const GET_USER_ID: u8 = 0x92;
+    pub const GET_UNITS: u8 = 0x93;
     pub const GET_SERIAL_NUMBER: u8 = 0x94;
     pub const GET_ODOMETER: u8 = 0x9b;
+    pub const GET_ERROR_CODE: u8 = 0x9c;
+    pub const GET_TWORK: u8 = 0xa0;
+    pub const GET_HORIZONTAL: u8 = 0xa1;
+    pub const GET_CALORIES: u8 = 0xa3;
+    pub const GET_PROGRAM: u8 = 0xa4;
+    pub const GET_PACE: u8 = 0xa6;
+    pub const GET_CADENCE: u8 = 0xa7;
+    pub const GET_USER_INFO: u8 = 0xab;
+    pub const GET_HEART_RATE: u8 = 0xb0;
+    pub const GET_POWER: u8 = 0xb4;
+
+    // Proprietary commands, only valid inside of `PROPRIETARY_COMMAND`.
+    pub const GET_EXTENDED_HR_BELT_INFO: u8 = 0x57;
+    pub const GET_FORCE_PLOT_DATA: u8 = 0x6b;
+    pub const GET_STROKE_STATS: u8 = 0x6e;
+    pub const GET_WORKOUT_TYPE: u8 = 0x89;
+    pub const GET_DISPLAY_TYPE: u8 = 0x8a;
+    pub const GET_DISPLAY_UNITS: u8 = 0x8b;
+    pub const GET_WORKOUT_STATE: u8 = 0x8d;
+    pub const GET_INTERVAL_TYPE: u8 = 0x8e;
+    pub const GET_OPERATIONAL_STATE: u8 = 0x8f;
+    pub const GET_ROWING_STATE: u8 = 0x93;
+    pub const GET_BATTERY_LEVEL: u8 = 0x97;
+    pub const GET_WORKOUT_INTERVAL_COUNT: u8 = 0x9f;
     pub const GET_WORK_TIME: u8 = 0xa0;
     pub const GET_WORK_DISTANCE: u8 = 0xa3;
+    pub const GET_STROKE_PACE: u8 = 0xa8;
+    pub const GET_STROKE_POWER: u8 = 0xa9;
+    pub const GET_STROKE_CALORIC_BURN_RATE: u8 = 0xaa;
+    pub const GET_SPLIT_AVG_PACE: u8 = 0xab;
+    pub const GET_SPLIT_AVG_POWER: u8 = 0xac;
+    pub const GET_TOTAL_AVG_PACE: u8 = 0xaf;
+    pub const GET_TOTAL_AVG_POWER: u8 = 0xb0;
+    pub const GET_TOTAL_AVG_CALORIES: u8 = 0xb2;
+    pub const GET_STROKE_RATE: u8 = 0xb3;
+    pub const GET_SPLIT_AVG_STROKE_RATE: u8 = 0xb4;
+    pub const GET_TOTAL_AVG_STROKE_RATE: u8 = 0xb5;
+    pub const GET_AVG_HEART_RATE: u8 = 0xb6;
+    pub const GET_LAST_SPLIT_TIME: u8 = 0xba;
+    pub const GET_LAST_SPLIT_DISTANCE: u8 = 0xbc;
+    pub const GET_TARGET_PACE_TIME: u8 = 0xbe;
+    pub const GET_STROKE_STATE: u8 = 0xbf;
+    pub const GET_DRAG_FACTOR: u8 = 0xc1;
+    pub const GET_ERROR_TYPE: u8 = 0xc8;
+    pub const GET_ERROR_VALUE: u8 = 0xc9;
+    pub const GET_REST_TIME: u8 = 0xcf;
+    pub const GET_ERG_MACHINE_TYPE: u8 = 0xed;
+
+    // Configuration commands, only valid inside of `PROPRIETARY_CONFIG_COMMAND`.
+    pub const SET_WORKOUT_TYPE: u8 = 0x01;
+    pub const SET_WORKOUT_DURATION: u8 = 0x03;
+    pub const SET_REST_DURATION: u8 = 0x04;
+    pub const SET_SPLIT_DURATION: u8 = 0x05;
+    pub const SET_TARGET_PACE_TIME: u8 = 0x06;
+    pub const SET_SCREEN_STATE: u8 = 0x13;
+    pub const CONFIGURE_WORKOUT: u8 = 0x14;
+    pub const SET_INTERVAL_TYPE: u8 = 0x17;
+    pub const SET_WORKOUT_INTERVAL_COUNT: u8 = 0x18;
 }
@@ -5,19 +5,27 @@
 /// All Concept2 commands sent through USB are formatted with CSAFE frames.
 ///
 /// CSAFE Frames come in two varieties: Standard and Extended. Standard is just data; Extended
-/// frames have a destination and source address in addition to the data.  As far as I can tell,
-/// we're only concerned with the Standard frames. Extended frames are additional functionality for
-/// other equipment that we don't care about.
-/// Regardless, everything starts with a Start Flag, then the Data, then the Checksum, and finally
-/// the Stop Flag.
+/// frames have a destination and source address in addition to the data. A single PM on a USB
+/// cable only needs Standard frames, but machines that are daisy-chained together are told
+/// apart by their addresses.
+/// Regardless, everything starts with a Start Flag (a different one for Extended frames), then
+/// the addresses if there are any, then the Data, then the Checksum, and finally the Stop Flag.
 ///
 /// The Checksum is gotten by doing bitwise XOR (no carry) with all of the bytes in the command,
-/// not including the Start Flag or the Stop Flag.
+/// not including the Start Flag, the addresses, or the Stop Flag.
 use crate::concept2command::Concept2Command;
 use crate::consts;
 
 pub struct CSAFEFrame {
     commands: Vec<Concept2Command>,
+    address: Option<FrameAddress>,
+}
+
+/// The addresses of an Extended frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameAddress {
+    pub destination: u8,
+    pub source: u8,
 }
 
 /// Checksum computes a single byte with exclusive OR on
@@ -35,7 +43,7 @@ fn checksum(commands: &[Concept2Command]) -> u8 {
 /// is in data, the byte is replaced with *two* bytes - `0xf3` and another
 /// byte. This is also done in reverse from the Concept2 machine; the response
 /// will have to be *unstuffed*.
-fn stuff_bytes(x: u8) -> Box<dyn Iterator<Item = u8>> {
+pub(crate) fn stuff_bytes(x: u8) -> Box<dyn Iterator<Item = u8>> {
     match x {
         0xf0 => Box::new(vec![0xf3, 0x00].into_iter()),
         0xf1 => Box::new(vec![0xf3, 0x01].into_iter()),
@@ -47,12 +55,44 @@ fn stuff_bytes(x: u8) -> Box<dyn Iterator<Item = u8>> {
 
 impl CSAFEFrame {
     pub fn new(cmds: Vec<Concept2Command>) -> CSAFEFrame {
-        CSAFEFrame { commands: cmds }
+        CSAFEFrame {
+            commands: cmds,
+            address: None,
+        }
+    }
+
+    /// An Extended frame, addressed to the machine at `destination` from the host.
+    pub fn extended(destination: u8, cmds: Vec<Concept2Command>) -> CSAFEFrame {
+        CSAFEFrame {
+            commands: cmds,
+            address: Some(FrameAddress {
+                destination,
+                source: consts::CSAFE_HOST_ADDRESS,
+            }),
+        }
+    }
+
+    pub fn commands(&self) -> &[Concept2Command] {
+        &self.commands
     }
+
+    /// `None` for Standard frames.
+    pub fn address(&self) -> Option<FrameAddress> {
+        self.address
+    }
+
     /// Resolves a CSAFEFrame to a vector of bytes. Every frame must start with a start flag,
     /// contain the byte representation of each CSAFE command, a checksum, and the stop flag.
     pub fn to_vec(&self) -> Vec<u8> {
-        std::iter::once(consts::CSAFE_START_FLAG)
+        let (start_flag, address) = match self.address {
+            Some(a) => (
+                consts::CSAFE_EXTENDED_START_FLAG,
+                vec![a.destination, a.source],
+            ),
+            None => (consts::CSAFE_START_FLAG, vec![]),
+        };
+        std::iter::once(start_flag)
+            .chain(address.into_iter().flat_map(stuff_bytes))
             .chain(
                 self.commands
                     .iter()
@@ -71,4 +111,81 @@ mod tests {
             crate::csafe::CSAFEFrame::new(vec![crate::concept2command::Concept2Command::GetStatus]);
         assert_eq!(vec![0xf1, 0x80, 0x80, 0xf2], cmd.to_vec());
     }
+
+    #[test]
+    fn test_extended_frame() {
+        let cmd = crate::csafe::CSAFEFrame::extended(
+            crate::consts::CSAFE_DEFAULT_SECONDARY_ADDRESS,
+            vec![crate::concept2command::Concept2Command::GetStatus],
+        );
+        assert_eq!(vec![0xf0, 0xfd, 0x00, 0x80, 0x80, 0xf2], cmd.to_vec());
+        // Addresses get stuffed, like everything else.
+        let cmd = crate::csafe::CSAFEFrame::extended(
+            0xf1,
+            vec![crate::concept2command::Concept2Command::GetStatus],
+        );
+        assert_eq!(vec![0xf0, 0xf3, 0x01, 0x00, 0x80, 0x80, 0xf2], cmd.to_vec());
+    }
+
+    #[test]
+    fn test_short_commands() {
+        let cmd = crate::csafe::CSAFEFrame::new(vec![
+            crate::concept2command::Concept2Command::GetPace,
+            crate::concept2command::Concept2Command::GetPower,
+            crate::concept2command::Concept2Command::GetHeartRate,
+        ]);
+        assert_eq!(vec![0xf1, 0xa6, 0xb4, 0xb0, 0xa2, 0xf2], cmd.to_vec());
+    }
+
+    #[test]
+    fn test_long_commands() {
+        let cmd = crate::csafe::CSAFEFrame::new(vec![
+            crate::concept2command::Concept2Command::SetHorizontal(
+                2000,
+                crate::units::Units::Meter,
+            ),
+            crate::concept2command::Concept2Command::SetProgram(0, 0),
+        ]);
+        assert_eq!(
+            vec![0xf1, 0x21, 0x3, 0xd0, 0x7, 0x24, 0x24, 0x2, 0x0, 0x0, 0xf7, 0xf2],
+            cmd.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_proprietary_long_command() {
+        let cmd = crate::csafe::CSAFEFrame::new(vec![
+            crate::concept2command::Concept2Command::ProprietaryCommand(vec![
+                crate::concept2command::Concept2ProprietaryCommand::GetWorkoutState,
+                crate::concept2command::Concept2ProprietaryCommand::GetStrokeStats,
+            ]),
+        ]);
+        assert_eq!(
+            vec![0xf1, 0x1a, 0x4, 0x8d, 0x6e, 0x1, 0x0, 0xfc, 0xf2],
+            cmd.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_proprietary_config_command() {
+        let cmd = crate::csafe::CSAFEFrame::new(vec![
+            crate::concept2command::Concept2Command::ProprietaryConfigCommand(vec![
+                crate::concept2command::Concept2ProprietaryConfigCommand::SetWorkoutType(
+                    crate::workout::WorkoutType::FixedDistanceSplits,
+                ),
+                crate::concept2command::Concept2ProprietaryConfigCommand::SetWorkoutDuration(
+                    crate::workout::DurationType::Distance,
+                    2000,
+                ),
+                crate::concept2command::Concept2ProprietaryConfigCommand::ConfigureWorkout(true),
+            ]),
+        ]);
+        assert_eq!(
+            vec![
+                0xf1, 0x76, 0xd, 0x1, 0x1, 0x3, 0x3, 0x5, 0x80, 0x0, 0x0, 0x7, 0xd0, 0x14, 0x1,
+                0x1, 0x3d, 0xf2
+            ],
+            cmd.to_vec()
+        );
+    }
 }
@@ -0,0 +1,199 @@
+/// Incremental decoding of response frames from a stream of bytes.
+///
+/// `parse_vec` and `parse_frame` want exactly one frame, behind a HID report number.
+/// Serial links and captured logs don't line up that neatly: a read can end in the middle
+/// of a frame, hold several frames, or start partway through one. `FrameDecoder` buffers
+/// whatever it's given and hands back frames as they're completed. Anything outside of a
+/// start flag and a stop flag - report numbers, HID padding, line noise - is skipped.
+use crate::concept2response::{self, CSAFEResponseFrame};
+use crate::consts;
+use crate::error::ParseError;
+
+/// The most bytes that are held for one frame, stuffing included. This is well past
+/// anything that the PM sends, which fits in one or two of its HID reports, so a start
+/// flag that's followed by this many bytes without a stop flag is taken to be noise.
+pub const MAX_FRAME_LENGTH: usize = 512;
+
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> FrameDecoder {
+        FrameDecoder::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete frame, or `None` if more bytes are needed. Frames that are
+    /// complete but invalid come back as errors, with offsets counted from their start
+    /// flag. A frame that's cut off by the start of another, or that runs past
+    /// `MAX_FRAME_LENGTH`, is reported as a `BadStopFlag`, and decoding carries on from
+    /// the next start flag.
+    pub fn next_frame(&mut self) -> Option<Result<CSAFEResponseFrame, ParseError>> {
+        let start = match self.buffer.iter().position(|&b| is_start_flag(b)) {
+            Some(start) => start,
+            None => {
+                self.buffer.clear();
+                return None;
+            }
+        };
+        self.buffer.drain(..start);
+        let limit = std::cmp::min(self.buffer.len(), MAX_FRAME_LENGTH);
+        let end = match self.buffer[1..limit]
+            .iter()
+            .position(|&b| is_start_flag(b) || b == consts::CSAFE_STOP_FLAG)
+        {
+            Some(i) => i + 1,
+            None if self.buffer.len() < MAX_FRAME_LENGTH => return None,
+            None => {
+                let offset = MAX_FRAME_LENGTH - 1;
+                let found = self.buffer[offset];
+                let next = self.buffer[offset..]
+                    .iter()
+                    .position(|&b| is_start_flag(b))
+                    .map_or(self.buffer.len(), |i| offset + i);
+                self.buffer.drain(..next);
+                return Some(Err(ParseError::BadStopFlag {
+                    offset,
+                    found: Some(found),
+                }));
+            }
+        };
+        if self.buffer[end] != consts::CSAFE_STOP_FLAG {
+            let found = self.buffer[end];
+            self.buffer.drain(..end);
+            return Some(Err(ParseError::BadStopFlag {
+                offset: end,
+                found: Some(found),
+            }));
+        }
+        let frame: Vec<u8> = self.buffer.drain(..=end).collect();
+        Some(concept2response::parse_frame_at(&frame, 0))
+    }
+
+    /// Pushes `bytes`, and returns every frame that they complete.
+    pub fn decode(&mut self, bytes: &[u8]) -> Vec<Result<CSAFEResponseFrame, ParseError>> {
+        self.push(bytes);
+        std::iter::from_fn(|| self.next_frame()).collect()
+    }
+
+    /// The number of bytes being held for an incomplete frame.
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Throws away anything buffered.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+fn is_start_flag(b: u8) -> bool {
+    b == consts::CSAFE_START_FLAG || b == consts::CSAFE_EXTENDED_START_FLAG
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_split_frame() {
+        // `test_compound_message` from `concept2response`, with its report number.
+        let v: Vec<u8> = vec![
+            0x1, 0xf1, 0x1, 0x1a, 0x11, 0xa0, 0x5, 0x0, 0x0, 0x0, 0x0, 0x0, 0xa3, 0x5, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x89, 0x1, 0x8, 0x94, 0x9, 0x34, 0x33, 0x30, 0x32, 0x32, 0x38, 0x35,
+            0x32, 0x35, 0x29, 0xf2,
+        ];
+        let mut decoder = super::FrameDecoder::new();
+        for chunk in v[..v.len() - 1].chunks(3) {
+            assert!(decoder.decode(chunk).is_empty());
+        }
+        let frames = decoder.decode(&v[v.len() - 1..]);
+        assert_eq!(vec![crate::concept2response::parse_frame(&v)], frames);
+        assert_eq!(0, decoder.pending());
+    }
+
+    #[test]
+    fn test_concatenated_frames() {
+        let user_id: Vec<u8> = vec![
+            0xf1, 0x81, 0x92, 0x5, 0x30, 0x30, 0x30, 0x30, 0x30, 0x26, 0xf2,
+        ];
+        let status: Vec<u8> = vec![0xf1, 0x81, 0x80, 0x1, 0x81, 0x81, 0xf2];
+        let mut stream: Vec<u8> = vec![0x00, 0x42];
+        stream.extend(&user_id);
+        stream.extend(&[0, 0, 0]);
+        stream.extend(&status);
+        let frames: Vec<crate::status::FrameStatus> = super::FrameDecoder::new()
+            .decode(&stream)
+            .into_iter()
+            .map(|f| f.unwrap().status)
+            .collect();
+        assert_eq!(2, frames.len());
+    }
+
+    #[test]
+    fn test_extended_frame() {
+        let frames = super::FrameDecoder::new().decode(&[
+            0x2, 0xf0, 0x0, 0xfd, 0x81, 0x80, 0x1, 0x81, 0x81, 0xf2, 0x0, 0x0,
+        ]);
+        assert_eq!(
+            Some(crate::consts::CSAFE_DEFAULT_SECONDARY_ADDRESS),
+            frames[0].as_ref().unwrap().address.map(|a| a.source)
+        );
+    }
+
+    #[test]
+    fn test_resync() {
+        let mut decoder = super::FrameDecoder::new();
+        // The first frame is cut off before its checksum and stop flag.
+        let frames = decoder.decode(&[
+            0xf1, 0x81, 0x92, 0x5, 0x30, 0xf1, 0x81, 0x80, 0x1, 0x81, 0x81, 0xf2,
+        ]);
+        assert_eq!(2, frames.len());
+        assert_eq!(
+            Err(crate::error::ParseError::BadStopFlag {
+                offset: 5,
+                found: Some(0xf1)
+            }),
+            frames[0]
+        );
+        assert!(frames[1].is_ok());
+
+        // A bad checksum doesn't stop the frames after it.
+        let frames = decoder.decode(&[
+            0xf1, 0x81, 0x80, 0x1, 0x81, 0x82, 0xf2, 0xf1, 0x81, 0x80, 0x1, 0x81, 0x81, 0xf2,
+        ]);
+        assert!(matches!(
+            frames[0],
+            Err(crate::error::ParseError::ChecksumMismatch { .. })
+        ));
+        assert!(frames[1].is_ok());
+    }
+    #[test]
+    fn test_runaway_frame() {
+        let mut decoder = super::FrameDecoder::new();
+        // A start flag, and then noise that never stops.
+        assert!(decoder.decode(&[0xf1]).is_empty());
+        for _ in 0..(super::MAX_FRAME_LENGTH - 2) / 50 {
+            assert!(decoder.decode(&[0x55; 50]).is_empty());
+        }
+        let mut noise = vec![0x55; 100];
+        noise.extend(&[0xf1, 0x81, 0x80, 0x1, 0x81, 0x81, 0xf2]);
+        let frames = decoder.decode(&noise);
+        assert_eq!(2, frames.len());
+        assert_eq!(
+            Err(crate::error::ParseError::BadStopFlag {
+                offset: super::MAX_FRAME_LENGTH - 1,
+                found: Some(0x55)
+            }),
+            frames[0]
+        );
+        assert!(frames[1].is_ok());
+
+        decoder.decode(&[0xf1]);
+        decoder.decode(&[0x55; 1000]);
+        assert_eq!(0, decoder.pending());
+    }
+}
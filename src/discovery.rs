@@ -0,0 +1,161 @@
+/// Finding the Concept2 monitors that are plugged in.
+///
+/// Enumeration order changes from boot to boot and from port to port, so monitors should
+/// be told apart by their USB serial number, which is printed on the PM and never
+/// changes. The HID path is what's needed to open one.
+use std::ffi::CString;
+
+use hidapi::{DeviceInfo, HidApi, HidDevice, HidResult};
+
+use crate::consts;
+use crate::hid_csafe::HidTransport;
+use crate::version::PMModel;
+
+/// An attached Performance Monitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorInfo {
+    pub path: CString,
+    pub serial_number: Option<String>,
+    pub product: Option<String>,
+    pub product_id: u16,
+    /// `None` if neither the product ID nor the product string says which it is.
+    pub model: Option<PMModel>,
+}
+
+impl MonitorInfo {
+    /// `None` if the device isn't made by Concept2.
+    pub fn from_device_info(info: &DeviceInfo) -> Option<MonitorInfo> {
+        if info.vendor_id() != consts::CONCEPT2_VENDOR_ID {
+            return None;
+        }
+        let product = info.product_string().map(String::from);
+        Some(MonitorInfo {
+            path: CString::from(info.path()),
+            serial_number: info.serial_number().map(String::from),
+            model: model_from_product_id(info.product_id())
+                .or_else(|| product.as_deref().and_then(model_from_product)),
+            product,
+            product_id: info.product_id(),
+        })
+    }
+
+    pub fn open(&self, api: &HidApi) -> HidResult<HidDevice> {
+        api.open_path(&self.path)
+    }
+
+    pub fn open_transport(&self, api: &HidApi) -> HidResult<HidTransport> {
+        self.open(api).map(HidTransport::new)
+    }
+}
+
+/// Each generation of PM has its own USB product ID.
+pub fn model_from_product_id(product_id: u16) -> Option<PMModel> {
+    match product_id {
+        consts::PM3_PRODUCT_ID => Some(PMModel::PM3),
+        consts::PM4_PRODUCT_ID => Some(PMModel::PM4),
+        _ => None,
+    }
+}
+
+/// The PM's USB product string names its generation, as in "Concept2 Performance
+/// Monitor 5 (PM5)". Only needed for product IDs that `model_from_product_id` doesn't
+/// know, which includes the PM5's.
+pub fn model_from_product(product: &str) -> Option<PMModel> {
+    if product.contains("PM5") {
+        Some(PMModel::PM5)
+    } else if product.contains("PM4") {
+        Some(PMModel::PM4)
+    } else if product.contains("PM3") {
+        Some(PMModel::PM3)
+    } else {
+        None
+    }
+}
+
+/// Every attached Concept2 monitor, ordered by serial number. A monitor that shows up
+/// more than once (one entry per HID interface, on some platforms) is only listed once.
+/// `api` should be refreshed first to see devices that were plugged in after it was
+/// created.
+pub fn discover(api: &HidApi) -> Vec<MonitorInfo> {
+    dedup(
+        api.device_list()
+            .filter_map(MonitorInfo::from_device_info)
+            .collect(),
+    )
+}
+
+/// Sorts by serial number, and keeps one entry per monitor: the one with the lowest path.
+/// Monitors without a serial number can only be told apart by their path.
+fn dedup(mut monitors: Vec<MonitorInfo>) -> Vec<MonitorInfo> {
+    monitors.sort_by(|a, b| {
+        a.serial_number
+            .cmp(&b.serial_number)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    monitors.dedup_by(|a, b| {
+        a.path == b.path || (a.serial_number.is_some() && a.serial_number == b.serial_number)
+    });
+    monitors
+}
+
+/// The attached monitor with this USB serial number.
+pub fn find_by_serial(api: &HidApi, serial_number: &str) -> Option<MonitorInfo> {
+    discover(api)
+        .into_iter()
+        .find(|m| m.serial_number.as_deref() == Some(serial_number))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_model() {
+        use crate::version::PMModel;
+        assert_eq!(
+            Some(PMModel::PM3),
+            super::model_from_product_id(crate::consts::PM3_PRODUCT_ID)
+        );
+        assert_eq!(
+            Some(PMModel::PM4),
+            super::model_from_product_id(crate::consts::PM4_PRODUCT_ID)
+        );
+        assert_eq!(None, super::model_from_product_id(0x1234));
+        assert_eq!(
+            Some(PMModel::PM3),
+            super::model_from_product("Concept2 Performance Monitor 3 (PM3)")
+        );
+        assert_eq!(
+            Some(PMModel::PM5),
+            super::model_from_product("Concept2 Performance Monitor 5 (PM5)")
+        );
+        assert_eq!(None, super::model_from_product("Something else"));
+    }
+
+    #[test]
+    fn test_dedup() {
+        let monitor = |path: &str, serial_number: Option<&str>| super::MonitorInfo {
+            path: std::ffi::CString::new(path).unwrap(),
+            serial_number: serial_number.map(String::from),
+            product: None,
+            product_id: crate::consts::CONCEPT2_PRODUCT_ID,
+            model: Some(crate::version::PMModel::PM5),
+        };
+        let monitors = super::dedup(vec![
+            monitor("/dev/hidraw3", Some("430000002")),
+            monitor("/dev/hidraw1", Some("430000001")),
+            // The same monitor, on its second interface.
+            monitor("/dev/hidraw2", Some("430000001")),
+            monitor("/dev/hidraw5", None),
+            monitor("/dev/hidraw4", None),
+        ]);
+        let paths: Vec<&str> = monitors.iter().map(|m| m.path.to_str().unwrap()).collect();
+        assert_eq!(
+            vec![
+                "/dev/hidraw4",
+                "/dev/hidraw5",
+                "/dev/hidraw1",
+                "/dev/hidraw3"
+            ],
+            paths
+        );
+    }
+}
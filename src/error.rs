@@ -0,0 +1,218 @@
+/// Error types for the library.
+///
+/// Every offset reported here is an index into the *unstuffed* bytes that were handed
+/// to the parser - that is, after `0xf3` escape pairs have been collapsed back into their
+/// original data byte. For `parse_vec`, offset 0 is the HID report number and offset 1 is
+/// the start flag.
+use std::fmt;
+use std::string::FromUtf8Error;
+
+use crate::status::FrameStatus;
+
+/// Everything that can go wrong while turning bytes from the Concept2 into
+/// `Concept2Response` structs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The frame did not begin with `CSAFE_START_FLAG`. `found` is `None` if there
+    /// was no byte at all.
+    BadStartFlag { offset: usize, found: Option<u8> },
+    /// The frame did not end with `CSAFE_STOP_FLAG`.
+    BadStopFlag { offset: usize, found: Option<u8> },
+    /// A `0xf3` escape byte was followed by something other than `0x00` through `0x03`.
+    InvalidStuffing { offset: usize, found: Option<u8> },
+    /// The checksum byte in the frame does not match the XOR of the frame contents.
+    /// `expected` is what the frame claimed, `actual` is what we computed.
+    ChecksumMismatch {
+        offset: usize,
+        expected: u8,
+        actual: u8,
+    },
+    /// The frame ended before a field was complete. `needed` is the number of bytes that
+    /// the field required, `available` is how many were actually left.
+    Truncated {
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
+    /// A response identifier that we don't know how to parse.
+    UnknownIdentifier { offset: usize, identifier: u8 },
+    /// A known response came back with a byte count that doesn't match its definition.
+    BadLength {
+        offset: usize,
+        identifier: u8,
+        expected: u8,
+        actual: u8,
+    },
+    /// A response that should contain ASCII text (serial number, user ID) did not.
+    InvalidUtf8 {
+        offset: usize,
+        source: FromUtf8Error,
+    },
+}
+
+impl ParseError {
+    /// The offset of the byte where parsing failed.
+    pub fn offset(&self) -> usize {
+        match self {
+            ParseError::BadStartFlag { offset, .. }
+            | ParseError::BadStopFlag { offset, .. }
+            | ParseError::InvalidStuffing { offset, .. }
+            | ParseError::ChecksumMismatch { offset, .. }
+            | ParseError::Truncated { offset, .. }
+            | ParseError::UnknownIdentifier { offset, .. }
+            | ParseError::BadLength { offset, .. }
+            | ParseError::InvalidUtf8 { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::BadStartFlag {
+                offset,
+                found: Some(b),
+            } => write!(
+                f,
+                "expected start flag at byte {}, found {:#04x}",
+                offset, b
+            ),
+            ParseError::BadStartFlag {
+                offset,
+                found: None,
+            } => {
+                write!(
+                    f,
+                    "expected start flag at byte {}, found end of input",
+                    offset
+                )
+            }
+            ParseError::BadStopFlag {
+                offset,
+                found: Some(b),
+            } => write!(f, "expected stop flag at byte {}, found {:#04x}", offset, b),
+            ParseError::BadStopFlag {
+                offset,
+                found: None,
+            } => {
+                write!(
+                    f,
+                    "expected stop flag at byte {}, found end of input",
+                    offset
+                )
+            }
+            ParseError::InvalidStuffing {
+                offset,
+                found: Some(b),
+            } => write!(
+                f,
+                "invalid escape sequence 0xf3 {:#04x} at byte {}",
+                b, offset
+            ),
+            ParseError::InvalidStuffing {
+                offset,
+                found: None,
+            } => {
+                write!(f, "dangling escape byte 0xf3 at byte {}", offset)
+            }
+            ParseError::ChecksumMismatch {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch at byte {}: frame says {:#04x}, computed {:#04x}",
+                offset, expected, actual
+            ),
+            ParseError::Truncated {
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "truncated frame at byte {}: needed {} bytes, only {} available",
+                offset, needed, available
+            ),
+            ParseError::UnknownIdentifier { offset, identifier } => write!(
+                f,
+                "unknown response identifier {:#04x} at byte {}",
+                identifier, offset
+            ),
+            ParseError::BadLength {
+                offset,
+                identifier,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "response {:#04x} at byte {} should have {} bytes of data, got {}",
+                identifier, offset, expected, actual
+            ),
+            ParseError::InvalidUtf8 { offset, source } => {
+                write!(f, "invalid UTF-8 at byte {}: {}", offset, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::InvalidUtf8 { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that can go wrong during a round trip to the Concept2 - either the
+/// transport failed, or what came back wasn't a valid response frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Concept2Error<E> {
+    /// The transport failed to send or receive.
+    Transport(E),
+    /// No response arrived before the timeout.
+    Timeout,
+    /// A response arrived, but it couldn't be parsed.
+    Parse(ParseError),
+    /// The machine didn't accept the frame. The status says why.
+    Rejected(FrameStatus),
+    /// The response to the command with this identifier was missing, or was the response
+    /// to some other command.
+    UnexpectedResponse { identifier: u8 },
+}
+
+impl<E> From<ParseError> for Concept2Error<E> {
+    fn from(e: ParseError) -> Concept2Error<E> {
+        Concept2Error::Parse(e)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Concept2Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Concept2Error::Transport(e) => write!(f, "transport error: {}", e),
+            Concept2Error::Timeout => write!(f, "timed out waiting for a response"),
+            Concept2Error::Parse(e) => write!(f, "invalid response: {}", e),
+            Concept2Error::Rejected(status) => write!(
+                f,
+                "frame was not accepted: {:?} in state {:?}",
+                status.previous_frame, status.state
+            ),
+            Concept2Error::UnexpectedResponse { identifier } => {
+                write!(f, "no matching response to command {:#04x}", identifier)
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for Concept2Error<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Concept2Error::Transport(e) => Some(e),
+            Concept2Error::Parse(e) => Some(e),
+            Concept2Error::Timeout
+            | Concept2Error::Rejected(_)
+            | Concept2Error::UnexpectedResponse { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,218 @@
+/// The force curve of each drive, and what can be read from its shape.
+///
+/// While the rower is driving, the PM5 collects samples of the force on the handle, and
+/// `GetForcePlotData` hands them out a block of up to 16 at a time, oldest first. A
+/// `ForceCurveReader` keeps reading blocks until the PM has nothing left, and puts each
+/// drive's samples back together into a `ForceCurve`. A drive is over once the rower has
+/// left the drive and the PM has sent its last samples, or when the next drive starts.
+/// The drive count from `GetStrokeStats` tells drives apart even when no poll lands in
+/// the recovery between them.
+use crate::command;
+use crate::error::Concept2Error;
+use crate::monitor::PerformanceMonitor;
+use crate::transport::Transport;
+use crate::workout::StrokeState;
+
+/// The most bytes of samples that the PM sends in one block.
+pub const BLOCK_BYTES: u8 = 32;
+
+/// The force on the handle through one drive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForceCurve {
+    /// Pounds, evenly spaced from the catch to the finish.
+    pub samples: Vec<u16>,
+}
+
+impl ForceCurve {
+    pub fn new(samples: Vec<u16>) -> ForceCurve {
+        ForceCurve { samples }
+    }
+
+    /// The highest force, in pounds.
+    pub fn peak(&self) -> u16 {
+        self.samples.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Where the first peak falls in the drive, from 0.0 at the catch to 1.0 at the
+    /// finish. A peak early in the drive means the legs are doing the work; a late one
+    /// means the back and arms are.
+    pub fn peak_position(&self) -> f64 {
+        let peak = self.peak();
+        match self.samples.iter().position(|&s| s == peak) {
+            Some(i) if self.samples.len() > 1 => i as f64 / (self.samples.len() - 1) as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// The area under the curve by the trapezoid rule, in pound-samples. Scaled by the
+    /// distance between samples, this is the work done on the handle.
+    pub fn area(&self) -> f64 {
+        self.samples
+            .windows(2)
+            .map(|w| (f64::from(w[0]) + f64::from(w[1])) / 2.0)
+            .sum()
+    }
+
+    /// How close the curve is to a single smooth hump, from 1.0 for a curve that only
+    /// rises to its peak and then only falls, down towards 0.0 for one that keeps
+    /// dipping and recovering. This is the rise and fall of an ideal curve with the same
+    /// ends and peak, over the curve's actual rise and fall.
+    pub fn smoothness(&self) -> f64 {
+        let (first, last) = match (self.samples.first(), self.samples.last()) {
+            (Some(&first), Some(&last)) => (f64::from(first), f64::from(last)),
+            _ => return 1.0,
+        };
+        let peak = f64::from(self.peak());
+        let ideal = (peak - first) + (peak - last);
+        let actual: f64 = self
+            .samples
+            .windows(2)
+            .map(|w| (f64::from(w[1]) - f64::from(w[0])).abs())
+            .sum();
+        if actual == 0.0 {
+            1.0
+        } else {
+            ideal / actual
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ForceCurveReader {
+    samples: Vec<u16>,
+    driving: bool,
+    /// The drive count that was read along with the last block.
+    drive_count: u16,
+}
+
+impl ForceCurveReader {
+    pub fn new() -> ForceCurveReader {
+        ForceCurveReader::default()
+    }
+
+    /// Takes one block of samples, and the stroke state and drive count that were read
+    /// along with it. Returns a drive's curve once it's complete. A block with fewer than
+    /// `BLOCK_BYTES / 2` samples is taken to mean that the PM had nothing more.
+    pub fn push(
+        &mut self,
+        state: StrokeState,
+        drive_count: u16,
+        block: &[u16],
+    ) -> Option<ForceCurve> {
+        let driving = state == StrokeState::Driving;
+        // The last drive never saw its end, so this block belongs to the next one.
+        let new_drive = !self.driving || drive_count != self.drive_count;
+        let previous = if driving && new_drive && !self.samples.is_empty() {
+            Some(ForceCurve::new(std::mem::take(&mut self.samples)))
+        } else {
+            None
+        };
+        self.driving = driving;
+        self.drive_count = drive_count;
+        self.samples.extend_from_slice(block);
+        if previous.is_some() {
+            return previous;
+        }
+        let drained = block.len() < usize::from(BLOCK_BYTES / 2);
+        if !driving && drained && !self.samples.is_empty() {
+            Some(ForceCurve::new(std::mem::take(&mut self.samples)))
+        } else {
+            None
+        }
+    }
+
+    /// Reads blocks until the PM has nothing more, and returns a drive's curve if that
+    /// finished one.
+    pub fn poll<T: Transport>(
+        &mut self,
+        pm: &mut PerformanceMonitor<T>,
+    ) -> Result<Option<ForceCurve>, Concept2Error<T::Error>> {
+        loop {
+            let (state, stats, block) = pm.execute((
+                command::GetStrokeState,
+                command::GetStrokeStats,
+                command::GetForcePlotData(BLOCK_BYTES),
+            ))?;
+            let full = block.len() == usize::from(BLOCK_BYTES / 2);
+            if let Some(curve) = self.push(state, stats.drive_count, &block) {
+                return Ok(Some(curve));
+            }
+            if !full {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Throws away the samples of a partly read drive.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.driving = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_metrics() {
+        let curve = super::ForceCurve::new(vec![0, 10, 30, 20, 25, 5]);
+        assert_eq!(30, curve.peak());
+        assert_eq!(0.4, curve.peak_position());
+        assert_eq!(87.5, curve.area());
+        assert_eq!(55.0 / 65.0, curve.smoothness());
+        assert_eq!(
+            1.0,
+            super::ForceCurve::new(vec![0, 10, 30, 20, 5]).smoothness()
+        );
+        assert_eq!(0, super::ForceCurve::new(vec![]).peak());
+    }
+
+    #[test]
+    fn test_read_drives() {
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_stroke_rate(24);
+        simulator.start_rowing();
+        let mut pm = crate::monitor::PerformanceMonitor::new(simulator);
+        let mut reader = super::ForceCurveReader::new();
+        let mut curves = vec![];
+        // Two strokes, read every 200 milliseconds.
+        for _ in 0..25 {
+            pm.transport_mut()
+                .advance(crate::units::Duration::from_millis(200));
+            if let Some(curve) = reader.poll(&mut pm).unwrap() {
+                curves.push(curve);
+            }
+        }
+        assert_eq!(2, curves.len());
+        for curve in curves {
+            // One sample every 100 milliseconds of an 833 millisecond drive, and one more
+            // when a sample lands right on the catch.
+            assert!(curve.samples.len() == 8 || curve.samples.len() == 9);
+            assert_eq!(1.0, curve.smoothness());
+            assert!(curve.peak_position() > 0.3 && curve.peak_position() < 0.7);
+        }
+    }
+    #[test]
+    fn test_read_across_recovery() {
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_stroke_rate(24);
+        simulator.start_rowing();
+        let mut pm = crate::monitor::PerformanceMonitor::new(simulator);
+        let mut reader = super::ForceCurveReader::new();
+        let mut curves = vec![];
+        // A stroke every 2.5 seconds, read 300 milliseconds into each drive, so that no
+        // read ever sees a recovery.
+        for i in 0..100 {
+            pm.transport_mut()
+                .advance(crate::units::Duration::from_millis(100));
+            if i % 25 == 2 {
+                if let Some(curve) = reader.poll(&mut pm).unwrap() {
+                    curves.push(curve);
+                }
+            }
+        }
+        assert_eq!(3, curves.len());
+        for curve in curves {
+            assert!(curve.samples.len() <= 4);
+        }
+    }
+}
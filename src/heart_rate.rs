@@ -0,0 +1,147 @@
+/// Heart rate from a belt that's paired with the PM.
+///
+/// The PM passes on the heart rate from its belt with `GetHeartRate`, and says which belt
+/// that is with `GetHeartRateBeltInfo`. A `HeartRate` is the two read together, along
+/// with what they say about the signal: a belt that's paired but sends nothing has
+/// dropped out, and a rate that no heart could beat at is noise. Only a good signal is
+/// given a training zone.
+use crate::command;
+use crate::concept2response::HeartRateBelt;
+use crate::error::Concept2Error;
+use crate::monitor::PerformanceMonitor;
+use crate::transport::Transport;
+
+/// The lowest and highest rates, in beats per minute, that are taken to be real.
+pub const MIN_PLAUSIBLE: u8 = 30;
+pub const MAX_PLAUSIBLE: u8 = 240;
+
+/// What a reading says about where it came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignalQuality {
+    /// A belt is paired with the PM.
+    pub paired: bool,
+    /// The PM has a heart rate to report.
+    pub receiving: bool,
+    /// The heart rate is within `MIN_PLAUSIBLE` and `MAX_PLAUSIBLE`.
+    pub plausible: bool,
+}
+
+impl SignalQuality {
+    pub fn is_good(&self) -> bool {
+        self.paired && self.receiving && self.plausible
+    }
+
+    /// Paired, but nothing is coming through - usually a belt that's too dry, or too far
+    /// from the PM.
+    pub fn is_dropout(&self) -> bool {
+        self.paired && !self.receiving
+    }
+}
+
+/// One reading of the heart rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeartRate {
+    /// Beats per minute. 0 when the PM has nothing to report.
+    pub bpm: u8,
+    /// `None` when no belt is paired.
+    pub belt: Option<HeartRateBelt>,
+    pub signal: SignalQuality,
+}
+
+impl HeartRate {
+    pub fn new(bpm: u8, belt: HeartRateBelt) -> HeartRate {
+        HeartRate {
+            bpm,
+            belt: if belt.is_paired() { Some(belt) } else { None },
+            signal: SignalQuality {
+                paired: belt.is_paired(),
+                receiving: bpm != 0,
+                plausible: (MIN_PLAUSIBLE..=MAX_PLAUSIBLE).contains(&bpm),
+            },
+        }
+    }
+
+    /// The training zone, from 1 at 50-60% of `max_heart_rate` up to 5 at 90% and over.
+    /// `None` below 50%, or if the signal isn't good.
+    pub fn zone(&self, max_heart_rate: u8) -> Option<u8> {
+        if !self.signal.is_good() || max_heart_rate == 0 {
+            return None;
+        }
+        let percent = u32::from(self.bpm) * 100 / u32::from(max_heart_rate);
+        match percent {
+            0..=49 => None,
+            _ => Some(std::cmp::min(percent / 10 - 4, 5) as u8),
+        }
+    }
+}
+
+/// Reads the heart rate and the belt in one frame.
+pub fn read<T: Transport>(
+    pm: &mut PerformanceMonitor<T>,
+) -> Result<HeartRate, Concept2Error<T::Error>> {
+    let (bpm, belt) = pm.execute((command::GetHeartRate, command::GetHeartRateBeltInfo))?;
+    Ok(HeartRate::new(bpm, belt))
+}
+
+#[cfg(test)]
+mod tests {
+    fn belt() -> crate::concept2response::HeartRateBelt {
+        crate::concept2response::HeartRateBelt {
+            manufacturer_id: 1,
+            device_type: 120,
+            belt_id: 0x0001_e240,
+        }
+    }
+
+    #[test]
+    fn test_signal() {
+        use crate::concept2response::HeartRateBelt;
+        let good = super::HeartRate::new(150, belt());
+        assert!(good.signal.is_good());
+        assert_eq!(Some(belt()), good.belt);
+        let dropout = super::HeartRate::new(0, belt());
+        assert!(dropout.signal.is_dropout());
+        assert!(!super::HeartRate::new(250, belt()).signal.plausible);
+        let unpaired = super::HeartRate::new(0, HeartRateBelt::default());
+        assert_eq!(None, unpaired.belt);
+        assert!(!unpaired.signal.is_dropout());
+    }
+
+    #[test]
+    fn test_zone() {
+        let zones: Vec<Option<u8>> = [80, 95, 110, 130, 150, 170, 185, 200]
+            .iter()
+            .map(|&bpm| super::HeartRate::new(bpm, belt()).zone(190))
+            .collect();
+        assert_eq!(
+            vec![
+                None,
+                Some(1),
+                Some(1),
+                Some(2),
+                Some(3),
+                Some(4),
+                Some(5),
+                Some(5)
+            ],
+            zones
+        );
+        assert_eq!(
+            None,
+            super::HeartRate::new(150, Default::default()).zone(190)
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_heart_rate(142);
+        simulator.set_heart_rate_belt(belt());
+        let mut pm = crate::monitor::PerformanceMonitor::new(simulator);
+        assert_eq!(Ok(super::HeartRate::new(142, belt())), super::read(&mut pm));
+        // The belt is part of the machine, not its settings, so it stays paired.
+        pm.execute(crate::concept2command::Concept2Command::Reset)
+            .unwrap();
+        assert_eq!(Ok(super::HeartRate::new(142, belt())), super::read(&mut pm));
+    }
+}
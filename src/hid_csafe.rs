@@ -1,47 +1,293 @@
 /// Library for sending and receiving bytes through HID devices.
-use hidapi::{HidDevice, HidResult};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use hidapi::{HidDevice, HidError, HidResult};
 
 use crate::consts;
 use crate::csafe;
+use crate::transport::Transport;
+
+/// The size of the Concept2's largest HID Report.
+pub(crate) const MSG_LENGTH: usize = 121;
+
+/// How long `read_hid` waits for a complete response.
+const READ_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The PM's HID reports: report ID, and the size of the report including the ID,
+/// smallest first.
+pub const REPORTS: [(u8, usize); 3] = [(1, 21), (4, 63), (2, 121)];
+
+/// Something went wrong talking to the Concept2 over HID.
+#[derive(Debug)]
+pub enum HidTransportError {
+    Hid(HidError),
+    /// The stuffed frame is `length` bytes long, but the largest report only has room
+    /// for `max`.
+    FrameTooLarge {
+        length: usize,
+        max: usize,
+    },
+}
+
+impl From<HidError> for HidTransportError {
+    fn from(e: HidError) -> HidTransportError {
+        HidTransportError::Hid(e)
+    }
+}
+
+impl fmt::Display for HidTransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HidTransportError::Hid(e) => write!(f, "{}", e),
+            HidTransportError::FrameTooLarge { length, max } => write!(
+                f,
+                "frame is {} bytes, but the largest HID report only holds {}",
+                length, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HidTransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HidTransportError::Hid(e) => Some(e),
+            HidTransportError::FrameTooLarge { .. } => None,
+        }
+    }
+}
 
-/// The size of the Concept2's HID Report.
-const MSG_LENGTH: usize = 121;
+/// The size of a report, including its ID, or `None` if the PM doesn't have it.
+pub fn report_length(report_num: u8) -> Option<usize> {
+    REPORTS
+        .iter()
+        .find(|&&(id, _)| id == report_num)
+        .map(|&(_, length)| length)
+}
+
+/// The smallest report that a frame of `length` stuffed bytes fits in, as a report ID
+/// and the size of the report.
+pub fn report_for(length: usize) -> Option<(u8, usize)> {
+    REPORTS
+        .iter()
+        .find(|&&(_, report_length)| length < report_length)
+        .copied()
+}
+
+/// Puts a frame into the smallest report that it fits in, padded with zeros.
+pub fn to_report(frame: &[u8]) -> Result<Vec<u8>, HidTransportError> {
+    let (report_num, report_length) =
+        report_for(frame.len()).ok_or(HidTransportError::FrameTooLarge {
+            length: frame.len(),
+            max: MSG_LENGTH - 1,
+        })?;
+    Ok(std::iter::once(report_num)
+        .chain(frame.iter().copied())
+        .chain(std::iter::repeat(0))
+        .take(report_length)
+        .collect())
+}
 
 pub fn write_read_csafe_cmd(
     device: &HidDevice,
-    report_num: u8,
     cmd: &csafe::CSAFEFrame,
-) -> HidResult<Vec<u8>> {
-    let msg: Vec<u8> = std::iter::once(report_num)
-        .chain(cmd.to_vec().into_iter())
-        .chain(std::iter::repeat(0))
-        .take(MSG_LENGTH)
-        .collect();
-    device.write(msg.as_slice()).map(|_| read_hid(&device))
-}
-
-/// Reads from the HID device until the data contains a STOP flag. This is because
-/// particularly large response frames can take multiple reads to populate - they're
-/// larger than the report size. It then puts them into a single vector.
-/// This is currently BUGGY - as it stands, there will be a bunch of zeros inside the
-/// resulting vector. A better treatment of this will involve reading exactly how
-/// many bytes were read for each report and using `iter::take_while` on each of the
-/// vectors to avoid the padded 0s.
-pub fn read_hid(device: &HidDevice) -> Vec<u8> {
-    let mut result: Vec<Vec<u8>> = Vec::new();
-    let mut recv_result: Vec<u8> = vec![0; MSG_LENGTH];
+) -> Result<Vec<u8>, HidTransportError> {
+    let msg = to_report(&cmd.to_vec())?;
+    drain(device)?;
+    device.write(msg.as_slice())?;
+    Ok(read_hid(device)?)
+}
+
+/// Reads from the HID device until a complete response frame has arrived. Particularly
+/// large response frames are bigger than a single report, so they take several reads.
+/// The result is the number of the first report that was read, followed by the frame,
+/// which is what `concept2response::parse_vec` expects. If nothing (or only part of a
+/// frame) arrives in time, the result is empty; a failed read is an error.
+pub fn read_hid(device: &HidDevice) -> HidResult<Vec<u8>> {
+    Ok(match read_frame(device, READ_TIMEOUT)? {
+        Some((report_num, frame)) => std::iter::once(report_num).chain(frame).collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Reads reports until `ReportAssembler` has a complete frame, or until `timeout` has
+/// passed. Returns the first report number along with the frame.
+fn read_frame(device: &HidDevice, timeout: Duration) -> HidResult<Option<(u8, Vec<u8>)>> {
+    let deadline = Instant::now() + timeout;
+    let mut assembler = ReportAssembler::new();
+    let mut report_num = None;
+    let mut report: Vec<u8> = vec![0; MSG_LENGTH];
     loop {
-        for elem in recv_result.iter_mut() {
-            *elem = 0;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let millis = std::cmp::min(remaining.as_millis(), i32::MAX as u128) as i32;
+        let read = device.read_timeout(report.as_mut_slice(), millis)?;
+        if read > 0 {
+            report_num.get_or_insert(report[0]);
+            if let Some(frame) = assembler.push(&report[..read]) {
+                return Ok(Some((report_num.unwrap_or(0), frame)));
+            }
+        } else if remaining == Duration::from_secs(0) {
+            return Ok(None);
         }
-        if device.read_timeout(recv_result.as_mut_slice(), 10).is_err()
-            || !recv_result
-                .iter()
-                .any(|&b| b == consts::CSAFE_START_FLAG || b == consts::CSAFE_STOP_FLAG)
-        {
-            break;
+    }
+}
+
+/// Puts response frames back together from the HID reports that carry them.
+///
+/// Every report starts with its report ID, and is padded with zeros out to the size of
+/// that report - 21, 63, or 121 bytes. Frames that don't fit in one report continue in
+/// the next. Anything before the start flag, and anything after the stop flag, is
+/// padding.
+#[derive(Debug, Default)]
+pub struct ReportAssembler {
+    buffer: Vec<u8>,
+    in_frame: bool,
+}
+
+impl ReportAssembler {
+    pub fn new() -> ReportAssembler {
+        ReportAssembler::default()
+    }
+
+    /// Takes one report, exactly as it was read: the report ID, followed by however many
+    /// bytes the read returned. Returns the frame, from start flag to stop flag, once
+    /// its last report has arrived.
+    pub fn push(&mut self, report: &[u8]) -> Option<Vec<u8>> {
+        let (&report_num, payload) = report.split_first()?;
+        let length = report_length(report_num).map_or(payload.len(), |length| {
+            std::cmp::min(length - 1, payload.len())
+        });
+        for &b in &payload[..length] {
+            match b {
+                // Stuffing keeps the start flag out of the data, so a start flag is
+                // always the start of a new frame, even if the last one never finished.
+                consts::CSAFE_START_FLAG | consts::CSAFE_EXTENDED_START_FLAG => {
+                    self.buffer.clear();
+                    self.buffer.push(b);
+                    self.in_frame = true;
+                }
+                consts::CSAFE_STOP_FLAG if self.in_frame => {
+                    self.buffer.push(b);
+                    self.in_frame = false;
+                    return Some(std::mem::take(&mut self.buffer));
+                }
+                _ if self.in_frame => self.buffer.push(b),
+                _ => (),
+            }
         }
-        result.push(recv_result.clone());
+        None
+    }
+
+    /// Throws away a partially assembled frame.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.in_frame = false;
+    }
+}
+
+/// Reads and throws away every report that's already waiting, without blocking.
+pub(crate) fn drain(device: &HidDevice) -> HidResult<()> {
+    let mut report: Vec<u8> = vec![0; MSG_LENGTH];
+    while device.read_timeout(report.as_mut_slice(), 0)? > 0 {}
+    Ok(())
+}
+
+/// A `Transport` over a Concept2 attached by USB.
+pub struct HidTransport {
+    device: HidDevice,
+}
+
+impl HidTransport {
+    pub fn new(device: HidDevice) -> HidTransport {
+        HidTransport { device }
+    }
+
+    pub fn device(&self) -> &HidDevice {
+        &self.device
+    }
+
+    pub fn into_inner(self) -> HidDevice {
+        self.device
+    }
+}
+
+impl Transport for HidTransport {
+    type Error = HidTransportError;
+
+    fn discard(&mut self) -> Result<(), HidTransportError> {
+        Ok(drain(&self.device)?)
+    }
+
+    fn send(&mut self, frame: &[u8]) -> Result<(), HidTransportError> {
+        let msg = to_report(frame)?;
+        self.device.write(msg.as_slice())?;
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, HidTransportError> {
+        Ok(read_frame(&self.device, timeout)?.map(|(_, frame)| frame))
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_reassemble_reports() {
+        // The compound message from `concept2response`'s tests, split over two of the
+        // smallest reports, with the padding that the PM adds.
+        let frame: Vec<u8> = vec![
+            0xf1, 0x1, 0x1a, 0x11, 0xa0, 0x5, 0x0, 0x0, 0x0, 0x0, 0x0, 0xa3, 0x5, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x89, 0x1, 0x8, 0x94, 0x9, 0x34, 0x33, 0x30, 0x32, 0x32, 0x38, 0x35, 0x32,
+            0x35, 0x29, 0xf2,
+        ];
+        let mut first = vec![1];
+        first.extend_from_slice(&frame[..20]);
+        let mut second = vec![1];
+        second.extend_from_slice(&frame[20..]);
+        second.resize(super::MSG_LENGTH, 0);
+
+        let mut assembler = super::ReportAssembler::new();
+        assert_eq!(None, assembler.push(&first));
+        assert_eq!(Some(frame.clone()), assembler.push(&second));
+
+        // Reads on some platforms return the whole buffer rather than the report size.
+        first.resize(super::MSG_LENGTH, 0);
+        assert_eq!(None, assembler.push(&first));
+        assert_eq!(Some(frame.clone()), assembler.push(&second));
+
+        // A large report needs no reassembly.
+        let mut large = vec![2];
+        large.extend_from_slice(&frame);
+        large.resize(super::MSG_LENGTH, 0);
+        assert_eq!(Some(frame), assembler.push(&large));
+    }
+
+    #[test]
+    fn test_report_length() {
+        assert_eq!(Some(21), super::report_length(1));
+        assert_eq!(Some(63), super::report_length(4));
+        assert_eq!(Some(121), super::report_length(2));
+        assert_eq!(None, super::report_length(3));
+    }
+
+    #[test]
+    fn test_report_selection() {
+        let cmd =
+            crate::csafe::CSAFEFrame::new(vec![crate::concept2command::Concept2Command::GetStatus]);
+        let report = super::to_report(&cmd.to_vec()).unwrap();
+        assert_eq!(21, report.len());
+        assert_eq!(&[1, 0xf1, 0x80, 0x80, 0xf2, 0], &report[..6]);
+
+        assert_eq!(Some((1, 21)), super::report_for(20));
+        assert_eq!(Some((4, 63)), super::report_for(21));
+        assert_eq!(Some((2, 121)), super::report_for(120));
+        assert_eq!(None, super::report_for(121));
+        assert!(matches!(
+            super::to_report(&[0; 121]),
+            Err(super::HidTransportError::FrameTooLarge {
+                length: 121,
+                max: 120
+            })
+        ));
     }
-    result.into_iter().flat_map(|v| v.into_iter()).collect()
 }
@@ -1,5 +1,27 @@
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod command;
 pub mod concept2command;
 pub mod concept2response;
 pub mod consts;
 pub mod csafe;
+pub mod decoder;
+pub mod discovery;
+pub mod error;
+pub mod force_curve;
+pub mod heart_rate;
 pub mod hid_csafe;
+pub mod manager;
+pub mod monitor;
+pub mod simulator;
+pub mod status;
+pub mod stroke;
+pub mod transport;
+pub mod units;
+pub mod version;
+pub mod workout;
+pub mod workout_builder;
+pub mod workout_monitor;
@@ -0,0 +1,38 @@
+//! Macros shared across the crate. This module is declared first in `lib.rs` so that
+//! every other module can use them.
+
+/// Defines a byte-sized enum along with lossless conversions to and from `u8`.
+macro_rules! byte_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident = $value:expr,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// A value that this library doesn't know about.
+            Unknown(u8),
+        }
+
+        impl From<u8> for $name {
+            fn from(b: u8) -> Self {
+                match b {
+                    $($value => $name::$variant,)*
+                    b => $name::Unknown(b),
+                }
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(x: $name) -> Self {
+                match x {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(b) => b,
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,462 @@
+/// Keeping track of monitors as they're unplugged and plugged back in.
+///
+/// A `DeviceManager` keeps one `PerformanceMonitor` per serial number. `poll` looks for
+/// monitors that have appeared or disappeared and reports them as `DeviceEvent`s, and
+/// `run` reconnects to a monitor whose transport has failed and tries again, so a bumped
+/// USB cable costs a single retry instead of a dead handle.
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use hidapi::{HidApi, HidError};
+
+use crate::discovery;
+use crate::error::Concept2Error;
+use crate::hid_csafe::HidTransport;
+use crate::monitor::PerformanceMonitor;
+use crate::transport::Transport;
+
+/// A way of finding monitors and opening them by serial number.
+pub trait Connector {
+    type Transport: Transport;
+    type Error;
+
+    /// The serial numbers of every monitor that is attached right now.
+    fn scan(&mut self) -> Result<Vec<String>, Self::Error>;
+
+    fn open(&mut self, serial_number: &str) -> Result<Self::Transport, Self::Error>;
+}
+
+/// Something went wrong finding or opening a monitor attached by USB.
+#[derive(Debug)]
+pub enum HidConnectorError {
+    Hid(HidError),
+    /// No attached monitor has this serial number.
+    NotFound(String),
+}
+
+impl From<HidError> for HidConnectorError {
+    fn from(e: HidError) -> HidConnectorError {
+        HidConnectorError::Hid(e)
+    }
+}
+
+impl fmt::Display for HidConnectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HidConnectorError::Hid(e) => write!(f, "{}", e),
+            HidConnectorError::NotFound(serial_number) => {
+                write!(f, "no monitor with serial number {}", serial_number)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HidConnectorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HidConnectorError::Hid(e) => Some(e),
+            HidConnectorError::NotFound(_) => None,
+        }
+    }
+}
+
+/// Monitors attached by USB.
+pub struct HidConnector {
+    api: HidApi,
+}
+
+impl HidConnector {
+    pub fn new() -> Result<HidConnector, HidError> {
+        HidApi::new().map(|api| HidConnector { api })
+    }
+
+    pub fn api(&self) -> &HidApi {
+        &self.api
+    }
+}
+
+impl Connector for HidConnector {
+    type Transport = HidTransport;
+    type Error = HidConnectorError;
+
+    /// Monitors without a serial number can't be told apart, so they're left out.
+    fn scan(&mut self) -> Result<Vec<String>, HidConnectorError> {
+        self.api.refresh_devices()?;
+        Ok(discovery::discover(&self.api)
+            .into_iter()
+            .filter_map(|m| m.serial_number)
+            .collect())
+    }
+
+    fn open(&mut self, serial_number: &str) -> Result<HidTransport, HidConnectorError> {
+        match discovery::find_by_serial(&self.api, serial_number) {
+            Some(monitor) => Ok(monitor.open_transport(&self.api)?),
+            None => Err(HidConnectorError::NotFound(String::from(serial_number))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent<E> {
+    Connected(String),
+    Disconnected(String),
+    /// The monitor is attached, but couldn't be opened. `poll` tries again each time
+    /// it's called, and reports each failure.
+    ConnectFailed(String, E),
+}
+
+/// Why `DeviceManager::run` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError<C, T> {
+    /// The monitor wasn't connected, and couldn't be opened.
+    Connect(C),
+    Monitor(Concept2Error<T>),
+}
+
+impl<C: fmt::Display, T: fmt::Display> fmt::Display for RunError<C, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Connect(e) => write!(f, "couldn't open the monitor: {}", e),
+            RunError::Monitor(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<C, T> std::error::Error for RunError<C, T>
+where
+    C: std::error::Error + 'static,
+    T: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Connect(e) => Some(e),
+            RunError::Monitor(e) => Some(e),
+        }
+    }
+}
+
+type RunResult<R, C> =
+    Result<R, RunError<<C as Connector>::Error, <<C as Connector>::Transport as Transport>::Error>>;
+
+pub struct DeviceManager<C: Connector> {
+    connector: C,
+    /// Every monitor that's being tracked, and its client if it's connected.
+    monitors: BTreeMap<String, Option<PerformanceMonitor<C::Transport>>>,
+    /// Whether to start tracking monitors that `poll` finds on its own.
+    track_new: bool,
+    events: VecDeque<DeviceEvent<C::Error>>,
+}
+
+impl<C: Connector> DeviceManager<C> {
+    /// A manager that tracks every monitor that it finds.
+    pub fn new(connector: C) -> DeviceManager<C> {
+        DeviceManager {
+            connector,
+            monitors: BTreeMap::new(),
+            track_new: true,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// A manager that only tracks the monitors given to `track`.
+    pub fn tracking_only(connector: C) -> DeviceManager<C> {
+        DeviceManager {
+            track_new: false,
+            ..DeviceManager::new(connector)
+        }
+    }
+
+    /// Starts tracking a monitor, whether or not it's attached yet.
+    pub fn track(&mut self, serial_number: &str) {
+        self.monitors
+            .entry(String::from(serial_number))
+            .or_insert(None);
+    }
+
+    /// Stops tracking a monitor, closing it if it's connected.
+    pub fn untrack(&mut self, serial_number: &str) {
+        self.monitors.remove(serial_number);
+    }
+
+    pub fn is_connected(&self, serial_number: &str) -> bool {
+        matches!(self.monitors.get(serial_number), Some(Some(_)))
+    }
+
+    /// The serial numbers of the monitors that are connected right now.
+    pub fn connected(&self) -> Vec<String> {
+        self.monitors
+            .iter()
+            .filter(|(_, m)| m.is_some())
+            .map(|(s, _)| s.clone())
+            .collect()
+    }
+
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
+    pub fn connector_mut(&mut self) -> &mut C {
+        &mut self.connector
+    }
+
+    /// Scans for monitors, connecting to tracked ones that have appeared and dropping
+    /// ones that have gone away. Returns everything that has happened since the last
+    /// call, including disconnects noticed by `run`.
+    pub fn poll(&mut self) -> Result<Vec<DeviceEvent<C::Error>>, C::Error> {
+        let attached = self.connector.scan()?;
+        if self.track_new {
+            for serial_number in attached.iter() {
+                self.track(serial_number);
+            }
+        }
+        let serial_numbers: Vec<String> = self.monitors.keys().cloned().collect();
+        for serial_number in serial_numbers {
+            let is_attached = attached.contains(&serial_number);
+            match (self.is_connected(&serial_number), is_attached) {
+                (false, true) => {
+                    if let Err(e) = self.connect(&serial_number) {
+                        self.events
+                            .push_back(DeviceEvent::ConnectFailed(serial_number, e));
+                    }
+                }
+                (true, false) => self.disconnect(&serial_number),
+                _ => (),
+            }
+        }
+        Ok(self.events.drain(..).collect())
+    }
+
+    fn connect(&mut self, serial_number: &str) -> Result<(), C::Error> {
+        let transport = self.connector.open(serial_number)?;
+        self.monitors.insert(
+            String::from(serial_number),
+            Some(PerformanceMonitor::new(transport)),
+        );
+        self.events
+            .push_back(DeviceEvent::Connected(String::from(serial_number)));
+        Ok(())
+    }
+
+    fn disconnect(&mut self, serial_number: &str) {
+        if let Some(monitor) = self.monitors.get_mut(serial_number) {
+            if monitor.take().is_some() {
+                self.events
+                    .push_back(DeviceEvent::Disconnected(String::from(serial_number)));
+            }
+        }
+    }
+
+    /// Runs `f` against a monitor. If the transport fails, the monitor is reopened and
+    /// `f` is run once more. Returns `None` if the monitor isn't tracked, and
+    /// `RunError::Connect` if it can't be opened, because it's been unplugged or for any
+    /// other reason.
+    pub fn run<R, F>(&mut self, serial_number: &str, mut f: F) -> Option<RunResult<R, C>>
+    where
+        F: FnMut(
+            &mut PerformanceMonitor<C::Transport>,
+        ) -> Result<R, Concept2Error<<C::Transport as Transport>::Error>>,
+    {
+        if !self.monitors.contains_key(serial_number) {
+            return None;
+        }
+        let mut retried = false;
+        loop {
+            if !self.is_connected(serial_number) {
+                if let Err(e) = self.connect(serial_number) {
+                    return Some(Err(RunError::Connect(e)));
+                }
+            }
+            let monitor = self.monitors.get_mut(serial_number)?.as_mut()?;
+            match f(monitor) {
+                Err(Concept2Error::Transport(e)) => {
+                    self.disconnect(serial_number);
+                    if retried {
+                        return Some(Err(RunError::Monitor(Concept2Error::Transport(e))));
+                    }
+                    retried = true;
+                }
+                result => return Some(result.map_err(RunError::Monitor)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeSet;
+    use std::rc::Rc;
+
+    /// A bench of simulators that can be unplugged.
+    struct Bench {
+        plugged_in: Rc<RefCell<BTreeSet<String>>>,
+        opened: usize,
+        /// Whether attached monitors can be opened.
+        permitted: bool,
+    }
+
+    struct Cable {
+        serial_number: String,
+        plugged_in: Rc<RefCell<BTreeSet<String>>>,
+        simulator: crate::simulator::Simulator,
+    }
+
+    impl Cable {
+        fn check(&self) -> Result<(), String> {
+            if self.plugged_in.borrow().contains(&self.serial_number) {
+                Ok(())
+            } else {
+                Err(String::from("unplugged"))
+            }
+        }
+    }
+
+    impl crate::transport::Transport for Cable {
+        type Error = String;
+
+        fn discard(&mut self) -> Result<(), String> {
+            self.check()?;
+            crate::transport::Transport::discard(&mut self.simulator).map_err(|e| match e {})
+        }
+
+        fn send(&mut self, frame: &[u8]) -> Result<(), String> {
+            self.check()?;
+            crate::transport::Transport::send(&mut self.simulator, frame).map_err(|e| match e {})
+        }
+
+        fn receive(&mut self, timeout: std::time::Duration) -> Result<Option<Vec<u8>>, String> {
+            self.check()?;
+            crate::transport::Transport::receive(&mut self.simulator, timeout)
+                .map_err(|e| match e {})
+        }
+    }
+
+    impl super::Connector for Bench {
+        type Transport = Cable;
+        type Error = String;
+
+        fn scan(&mut self) -> Result<Vec<String>, String> {
+            Ok(self.plugged_in.borrow().iter().cloned().collect())
+        }
+
+        fn open(&mut self, serial_number: &str) -> Result<Cable, String> {
+            if !self.plugged_in.borrow().contains(serial_number) {
+                return Err(String::from("unplugged"));
+            }
+            if !self.permitted {
+                return Err(String::from("permission denied"));
+            }
+            self.opened += 1;
+            let mut simulator = crate::simulator::Simulator::new();
+            simulator.set_serial_number(serial_number);
+            Ok(Cable {
+                serial_number: String::from(serial_number),
+                plugged_in: self.plugged_in.clone(),
+                simulator,
+            })
+        }
+    }
+
+    #[test]
+    fn test_hot_plug() {
+        use super::DeviceEvent;
+        let plugged_in = Rc::new(RefCell::new(BTreeSet::new()));
+        plugged_in.borrow_mut().insert(String::from("430000001"));
+        let mut manager = super::DeviceManager::new(Bench {
+            plugged_in: plugged_in.clone(),
+            opened: 0,
+            permitted: true,
+        });
+        assert_eq!(
+            Ok(vec![DeviceEvent::Connected(String::from("430000001"))]),
+            manager.poll()
+        );
+        assert_eq!(
+            Some(Ok(String::from("430000001"))),
+            manager.run("430000001", |pm| pm.serial_number())
+        );
+
+        // Unplugged: the manager notices on the next poll.
+        plugged_in.borrow_mut().remove("430000001");
+        assert_eq!(
+            Ok(vec![DeviceEvent::Disconnected(String::from("430000001"))]),
+            manager.poll()
+        );
+        assert_eq!(
+            Some(Err(super::RunError::Connect(String::from("unplugged")))),
+            manager.run("430000001", |pm| pm.serial_number())
+        );
+        assert_eq!(None, manager.run("430000002", |pm| pm.serial_number()));
+
+        // Plugged back in.
+        plugged_in.borrow_mut().insert(String::from("430000001"));
+        assert_eq!(
+            Ok(vec![DeviceEvent::Connected(String::from("430000001"))]),
+            manager.poll()
+        );
+        assert!(manager.is_connected("430000001"));
+    }
+
+    #[test]
+    fn test_reconnect_on_failure() {
+        use super::DeviceEvent;
+        let plugged_in = Rc::new(RefCell::new(BTreeSet::new()));
+        plugged_in.borrow_mut().insert(String::from("430000001"));
+        let mut manager = super::DeviceManager::new(Bench {
+            plugged_in: plugged_in.clone(),
+            opened: 0,
+            permitted: true,
+        });
+        manager.poll().unwrap();
+
+        // A bumped cable: the first attempt fails, but the monitor is back by the time
+        // the manager reopens it.
+        let mut first = true;
+        let result = manager.run("430000001", |pm| {
+            if first {
+                first = false;
+                return Err(crate::error::Concept2Error::Transport(String::from(
+                    "unplugged",
+                )));
+            }
+            pm.serial_number()
+        });
+        assert_eq!(Some(Ok(String::from("430000001"))), result);
+        assert_eq!(2, manager.connector().opened);
+        assert_eq!(
+            Ok(vec![
+                DeviceEvent::Disconnected(String::from("430000001")),
+                DeviceEvent::Connected(String::from("430000001")),
+            ]),
+            manager.poll()
+        );
+    }
+    #[test]
+    fn test_connect_failed() {
+        use super::DeviceEvent;
+        let plugged_in = Rc::new(RefCell::new(BTreeSet::new()));
+        plugged_in.borrow_mut().insert(String::from("430000001"));
+        let mut manager = super::DeviceManager::new(Bench {
+            plugged_in,
+            opened: 0,
+            permitted: false,
+        });
+        // Every poll tries again, and reports the failure again.
+        for _ in 0..2 {
+            assert_eq!(
+                Ok(vec![DeviceEvent::ConnectFailed(
+                    String::from("430000001"),
+                    String::from("permission denied")
+                )]),
+                manager.poll()
+            );
+        }
+        assert!(!manager.is_connected("430000001"));
+
+        manager.connector_mut().permitted = true;
+        assert_eq!(
+            Ok(vec![DeviceEvent::Connected(String::from("430000001"))]),
+            manager.poll()
+        );
+    }
+}
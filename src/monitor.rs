@@ -0,0 +1,333 @@
+/// A client for a Performance Monitor that hides the frames and bytes.
+///
+/// Each method sends one frame over the `Transport`, checks that the machine accepted it
+/// and that every response answers the command that asked for it, and returns the typed
+/// value. `execute` is available for sending any `command::Command`, or several of them
+/// in one frame.
+use std::time::Duration;
+
+use crate::command::{self, Batch};
+use crate::concept2command::{Concept2Command, Concept2ProprietaryConfigCommand};
+use crate::concept2response::{CSAFEResponseFrame, Concept2Response, StrokeStats};
+use crate::csafe::CSAFEFrame;
+use crate::error::Concept2Error;
+use crate::status::{FrameStatus, PreviousFrameStatus};
+use crate::transport::{self, Transport};
+use crate::units::{Distance, Measurement};
+use crate::version::{ErgType, VersionInfo};
+use crate::workout::{WorkoutState, WorkoutType};
+
+pub struct PerformanceMonitor<T: Transport> {
+    transport: T,
+    timeout: Duration,
+}
+
+type Result<T, E> = std::result::Result<T, Concept2Error<E>>;
+
+impl<T: Transport> PerformanceMonitor<T> {
+    pub fn new(transport: T) -> PerformanceMonitor<T> {
+        PerformanceMonitor {
+            transport,
+            timeout: transport::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// How long to wait for each response.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Sends a command, or a batch of them, in a single frame, and returns the typed
+    /// responses. The responses must come back in the same order as the commands,
+    /// including the ones nested inside of the wrappers.
+    pub fn execute<B: Batch>(&mut self, batch: B) -> Result<B::Output, T::Error> {
+        let commands = batch.commands();
+        let frame = transport::round_trip(
+            &mut self.transport,
+            &CSAFEFrame::new(commands.clone()),
+            self.timeout,
+        )?;
+        finish(&batch, &commands, frame)
+    }
+
+    /// Sends configuration commands, which have nothing to say in response.
+    pub fn configure(
+        &mut self,
+        commands: Vec<Concept2ProprietaryConfigCommand>,
+    ) -> Result<(), T::Error> {
+        self.execute(vec![Concept2Command::ProprietaryConfigCommand(commands)])
+            .map(|_| ())
+    }
+
+    pub fn status(&mut self) -> Result<FrameStatus, T::Error> {
+        self.execute(command::GetStatus)
+    }
+
+    pub fn version(&mut self) -> Result<VersionInfo, T::Error> {
+        self.execute(command::GetVersion)
+    }
+
+    pub fn serial_number(&mut self) -> Result<String, T::Error> {
+        self.execute(command::GetSerialNumber)
+    }
+
+    pub fn user_id(&mut self) -> Result<String, T::Error> {
+        self.execute(command::GetUserID)
+    }
+
+    /// Lifetime distance rowed on this machine.
+    pub fn odometer(&mut self) -> Result<Distance, T::Error> {
+        self.execute(command::GetOdometer)
+    }
+
+    pub fn heart_rate(&mut self) -> Result<u8, T::Error> {
+        self.execute(command::GetHeartRate)
+    }
+
+    pub fn power(&mut self) -> Result<Measurement, T::Error> {
+        self.execute(command::GetPower)
+    }
+
+    /// Time into the current workout, or the current interval.
+    pub fn work_time(&mut self) -> Result<Duration, T::Error> {
+        self.execute(command::GetWorkTime)
+    }
+
+    /// Distance into the current workout, or the current interval.
+    pub fn work_distance(&mut self) -> Result<Distance, T::Error> {
+        self.execute(command::GetWorkDistance)
+    }
+
+    pub fn workout_type(&mut self) -> Result<WorkoutType, T::Error> {
+        self.execute(command::GetWorkoutType)
+    }
+
+    pub fn workout_state(&mut self) -> Result<WorkoutState, T::Error> {
+        self.execute(command::GetWorkoutState)
+    }
+
+    /// Time per 500 meters, for the most recent stroke.
+    pub fn stroke_pace(&mut self) -> Result<Duration, T::Error> {
+        self.execute(command::GetStrokePace)
+    }
+
+    /// Strokes per minute.
+    pub fn stroke_rate(&mut self) -> Result<u8, T::Error> {
+        self.execute(command::GetStrokeRate)
+    }
+
+    pub fn stroke_stats(&mut self) -> Result<StrokeStats, T::Error> {
+        self.execute(command::GetStrokeStats)
+    }
+
+    pub fn drag_factor(&mut self) -> Result<u8, T::Error> {
+        self.execute(command::GetDragFactor)
+    }
+
+    /// Whether this is a rower, a SkiErg, or a BikeErg.
+    pub fn erg_type(&mut self) -> Result<ErgType, T::Error> {
+        self.execute(command::GetErgMachineType).map(ErgType::from)
+    }
+}
+
+/// Checks that the machine accepted the frame and answered every command, and takes the
+/// batch's output from the responses.
+pub(crate) fn finish<B: Batch, E>(
+    batch: &B,
+    commands: &[Concept2Command],
+    frame: CSAFEResponseFrame,
+) -> Result<B::Output, E> {
+    if frame.status.previous_frame != PreviousFrameStatus::Ok {
+        return Err(Concept2Error::Rejected(frame.status));
+    }
+    correlate(commands, &frame.responses)?;
+    batch
+        .output(frame)
+        .map_err(|identifier| Concept2Error::UnexpectedResponse { identifier })
+}
+
+fn unexpected<E>(response: &Concept2Response) -> Concept2Error<E> {
+    Concept2Error::UnexpectedResponse {
+        identifier: response.identifier(),
+    }
+}
+
+/// Checks that there is exactly one response for each command, in order.
+fn correlate<E>(
+    commands: &[Concept2Command],
+    responses: &[Concept2Response],
+) -> std::result::Result<(), Concept2Error<E>> {
+    for (i, command) in commands.iter().enumerate() {
+        let identifier = command.identifier();
+        let response = responses
+            .get(i)
+            .filter(|r| r.identifier() == identifier)
+            .ok_or(Concept2Error::UnexpectedResponse { identifier })?;
+        match (command, response) {
+            (
+                Concept2Command::ProprietaryCommand(commands),
+                Concept2Response::ProprietaryCommand(responses),
+            ) => correlate_nested(
+                commands.iter().map(|c| c.identifier()),
+                responses.iter().map(|r| r.identifier()),
+            )?,
+            (
+                Concept2Command::ProprietaryConfigCommand(commands),
+                Concept2Response::ProprietaryConfigCommand(responses),
+            ) => correlate_nested(
+                commands.iter().map(|c| c.identifier()),
+                responses.iter().map(|r| r.identifier()),
+            )?,
+            _ => (),
+        }
+    }
+    match responses.get(commands.len()) {
+        Some(r) => Err(unexpected(r)),
+        None => Ok(()),
+    }
+}
+
+fn correlate_nested<E>(
+    mut commands: impl Iterator<Item = u8>,
+    mut responses: impl Iterator<Item = u8>,
+) -> std::result::Result<(), Concept2Error<E>> {
+    loop {
+        match (commands.next(), responses.next()) {
+            (None, None) => return Ok(()),
+            (Some(c), Some(r)) if c == r => (),
+            (Some(identifier), _) | (None, Some(identifier)) => {
+                return Err(Concept2Error::UnexpectedResponse { identifier })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_monitor() {
+        let mut sim = crate::simulator::Simulator::new();
+        sim.set_odometer(2172148);
+        let mut pm = super::PerformanceMonitor::new(sim);
+        assert_eq!("430000000", pm.serial_number().unwrap());
+        assert_eq!(
+            crate::units::Distance::from_meters(2172148),
+            pm.odometer().unwrap()
+        );
+        assert_eq!(
+            crate::workout::WorkoutType::JustRowNoSplits,
+            pm.workout_type().unwrap()
+        );
+        assert_eq!(crate::status::SlaveState::Ready, pm.status().unwrap().state);
+        assert_eq!(crate::version::ErgType::Rower, pm.erg_type().unwrap());
+
+        pm.transport_mut().start_rowing();
+        pm.transport_mut()
+            .advance(crate::units::Duration::from_secs(60));
+        assert_eq!(
+            crate::units::Duration::from_secs(60),
+            pm.work_time().unwrap()
+        );
+        assert_eq!(
+            Some(250.0),
+            pm.work_distance().unwrap().meters().map(f64::round)
+        );
+        assert_eq!(crate::status::SlaveState::InUse, pm.status().unwrap().state);
+    }
+
+    #[test]
+    fn test_typed_batch() {
+        use crate::command::{GetOdometer, GetSerialNumber, GetStrokeRate, GetWorkTime};
+        let mut sim = crate::simulator::Simulator::new();
+        sim.set_odometer(2172148);
+        sim.set_stroke_rate(26);
+        let mut pm = super::PerformanceMonitor::new(sim);
+        assert_eq!(
+            crate::units::Distance::from_meters(2172148),
+            pm.execute(GetOdometer).unwrap()
+        );
+
+        pm.transport_mut().start_rowing();
+        pm.transport_mut()
+            .advance(crate::units::Duration::from_secs(30));
+        let (serial_number, time, rate) = pm
+            .execute((GetSerialNumber, GetWorkTime, GetStrokeRate))
+            .unwrap();
+        assert_eq!("430000000", serial_number);
+        assert_eq!(crate::units::Duration::from_secs(30), time);
+        assert_eq!(26, rate);
+
+        use crate::command::{GetProgram, GetUserInfo};
+        use crate::concept2command::Concept2Command;
+        use crate::units::Units;
+        pm.execute(vec![
+            Concept2Command::SetProgram(4, 2),
+            Concept2Command::SetUserInfo(80, Units::Kilogram, 40, 1),
+        ])
+        .unwrap();
+        assert_eq!(
+            (
+                (4, 2),
+                (crate::units::Measurement::new(80, Units::Kilogram), 40, 1)
+            ),
+            pm.execute((GetProgram, GetUserInfo)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_correlate() {
+        use crate::concept2command::{Concept2Command, Concept2ProprietaryCommand};
+        use crate::concept2response::{Concept2Response, Concept2ResponseProprietary};
+        let commands = vec![
+            Concept2Command::GetHeartRate,
+            Concept2Command::ProprietaryCommand(vec![
+                Concept2ProprietaryCommand::GetDragFactor,
+                Concept2ProprietaryCommand::GetStrokeRate,
+            ]),
+        ];
+        let result: Result<(), crate::error::Concept2Error<()>> = super::correlate(
+            &commands,
+            &[
+                Concept2Response::GetHeartRate(140),
+                Concept2Response::ProprietaryCommand(vec![
+                    Concept2ResponseProprietary::GetDragFactor(120),
+                    Concept2ResponseProprietary::GetStrokeRate(24),
+                ]),
+            ],
+        );
+        assert!(result.is_ok());
+
+        let result: Result<(), crate::error::Concept2Error<()>> = super::correlate(
+            &commands,
+            &[
+                Concept2Response::GetHeartRate(140),
+                Concept2Response::ProprietaryCommand(vec![
+                    Concept2ResponseProprietary::GetDragFactor(120),
+                ]),
+            ],
+        );
+        assert!(matches!(
+            result,
+            Err(crate::error::Concept2Error::UnexpectedResponse { identifier: 0xb3 })
+        ));
+
+        let result: Result<(), crate::error::Concept2Error<()>> =
+            super::correlate(&commands, &[Concept2Response::GetHeartRate(140)]);
+        assert!(matches!(
+            result,
+            Err(crate::error::Concept2Error::UnexpectedResponse { identifier: 0x1a })
+        ));
+    }
+}
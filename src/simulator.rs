@@ -0,0 +1,1218 @@
+/// A simulated Performance Monitor, for testing without a rower attached.
+///
+/// `Simulator` is a `Transport`: frames sent to it are decoded and answered the same way
+/// a PM5 would, with stuffed and checksummed response frames. It keeps the state that the
+/// commands in this library can read and write - the CSAFE state machine, the programmed
+/// workout, and the progress through it - and rows at a fixed pace and stroke rate while
+/// `start_rowing` is in effect.
+///
+/// Extended frames are answered with Extended frames, but only if they are addressed to the
+/// simulator or broadcast; anything else goes unanswered, as it would on a shared bus.
+///
+/// Simulated time only passes when `advance` is called, so that tests are deterministic.
+/// Malformed frames are answered with `PreviousFrameStatus::Bad` in the status byte, and
+/// frames containing commands that the simulator doesn't know are answered with
+/// `PreviousFrameStatus::Rejected`, just as the real thing does.
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+use crate::concept2response::HeartRateBelt;
+use crate::consts::{self, csafe_commands};
+use crate::csafe::{self, FrameAddress};
+use crate::status::{FrameStatus, PreviousFrameStatus, SlaveState};
+use crate::transport::Transport;
+use crate::units::{self, Duration, Units};
+use crate::version::{ErgMachineType, VersionInfo, CONCEPT2_MANUFACTURER_ID};
+use crate::workout::{
+    DurationType, IntervalType, OperationalState, RowingState, ScreenType, StrokeState,
+    WorkoutScreen, WorkoutState, WorkoutType,
+};
+
+/// Simulated time is integrated in steps no longer than this.
+const STEP: Duration = Duration::from_millis(100);
+
+/// The fraction of each stroke spent on the drive.
+const DRIVE_FRACTION: f64 = 1.0 / 3.0;
+
+/// Hundredths of a meter.
+const STROKE_LENGTH: u8 = 140;
+
+const SERIAL_NUMBER_LENGTH: usize = 9;
+
+/// The fastest pace that the simulator rows at, per 500 meters - well past any human.
+pub const MIN_PACE: Duration = Duration::from_secs(30);
+
+/// How far along the current workout (or interval) is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Progress {
+    time: Duration,
+    meters: f64,
+    calories: f64,
+    /// Watt-seconds.
+    energy: f64,
+    strokes: f64,
+}
+
+impl Progress {
+    fn new() -> Progress {
+        Progress {
+            time: Duration::from_secs(0),
+            meters: 0.0,
+            calories: 0.0,
+            energy: 0.0,
+            strokes: 0.0,
+        }
+    }
+
+    /// Whether this much work satisfies a duration of the given type.
+    fn reached(&self, duration_type: DurationType, value: u32) -> bool {
+        match duration_type {
+            DurationType::Time => units::to_hundredths(self.time) >= value,
+            DurationType::Distance => self.meters >= f64::from(value),
+            DurationType::Calories => self.calories >= f64::from(value),
+            DurationType::WattMinutes => self.energy / 60.0 >= f64::from(value),
+            DurationType::Unknown(_) => false,
+        }
+    }
+}
+
+pub struct Simulator {
+    serial_number: String,
+    user_id: String,
+    version: VersionInfo,
+    erg_machine_type: ErgMachineType,
+    address: u8,
+    state: SlaveState,
+    frame_toggle: bool,
+    responses: VecDeque<Vec<u8>>,
+
+    // Values written by the public CSAFE `Set` commands.
+    id_digits: u8,
+    time: (u8, u8, u8),
+    date: (u8, u8, u8),
+    timeout: u8,
+    horizontal: (u16, Units),
+    calories_goal: u16,
+    program: (u8, u8),
+    user_info: (u16, Units, u8, u8),
+    power_goal: (u16, Units),
+    auto_upload: u8,
+
+    // The rower.
+    rowing: bool,
+    pace: Duration,
+    stroke_rate: u8,
+    heart_rate: u8,
+    heart_rate_belt: HeartRateBelt,
+    drag_factor: u8,
+    odometer: f64,
+
+    // The programmed workout.
+    workout_type: WorkoutType,
+    workout_duration: (DurationType, u32),
+    split_duration: (DurationType, u32),
+    rest_duration: Duration,
+    target_pace: Duration,
+    interval_type: IntervalType,
+    screen: (ScreenType, u8),
+    configuring: bool,
+
+    // Progress through the workout.
+    workout_state: WorkoutState,
+    interval_count: u8,
+    interval: Progress,
+    total: Progress,
+    split: Progress,
+    rest_remaining: Duration,
+    last_split: (Duration, f64),
+    heart_beats: f64,
+    /// Force samples from the current drive that haven't been read yet.
+    force_plot: VecDeque<u16>,
+}
+
+impl Default for Simulator {
+    fn default() -> Simulator {
+        Simulator::new()
+    }
+}
+
+impl Simulator {
+    /// A PM5 on a rower, sitting on the main screen with a Just Row workout, set to row
+    /// at 2:00/500m and 24 strokes per minute once started.
+    pub fn new() -> Simulator {
+        Simulator {
+            serial_number: String::from("430000000"),
+            user_id: String::from("00000"),
+            version: VersionInfo {
+                manufacturer_id: CONCEPT2_MANUFACTURER_ID,
+                class_id: 2,
+                model: 5,
+                hardware_version: 634,
+                software_version: 210,
+            },
+            erg_machine_type: ErgMachineType::StaticD,
+            address: consts::CSAFE_DEFAULT_SECONDARY_ADDRESS,
+            state: SlaveState::Ready,
+            frame_toggle: false,
+            responses: VecDeque::new(),
+            id_digits: 5,
+            time: (0, 0, 0),
+            date: (0, 1, 1),
+            timeout: 0,
+            horizontal: (0, Units::Meter),
+            calories_goal: 0,
+            program: (0, 0),
+            user_info: (0, Units::Kilogram, 0, 0),
+            power_goal: (0, Units::Watts),
+            auto_upload: 0,
+            rowing: false,
+            pace: Duration::from_secs(120),
+            stroke_rate: 24,
+            heart_rate: 0,
+            heart_rate_belt: HeartRateBelt::default(),
+            drag_factor: 120,
+            odometer: 0.0,
+            workout_type: WorkoutType::JustRowNoSplits,
+            workout_duration: (DurationType::Time, 0),
+            split_duration: (DurationType::Time, 0),
+            rest_duration: Duration::from_secs(0),
+            target_pace: Duration::from_secs(0),
+            interval_type: IntervalType::None,
+            screen: (ScreenType::Workout, u8::from(WorkoutScreen::GoToMainScreen)),
+            configuring: false,
+            workout_state: WorkoutState::WaitToBegin,
+            interval_count: 0,
+            interval: Progress::new(),
+            total: Progress::new(),
+            split: Progress::new(),
+            rest_remaining: Duration::from_secs(0),
+            last_split: (Duration::from_secs(0), 0.0),
+            heart_beats: 0.0,
+            force_plot: VecDeque::new(),
+        }
+    }
+
+    /// Real serial numbers are nine ASCII characters, so anything else is dropped, the
+    /// rest is cut off after nine, and a shorter one is padded with leading zeros.
+    pub fn set_serial_number(&mut self, serial_number: &str) {
+        let serial_number: String = serial_number
+            .chars()
+            .filter(char::is_ascii)
+            .take(SERIAL_NUMBER_LENGTH)
+            .collect();
+        self.serial_number = format!("{:0>1$}", serial_number, SERIAL_NUMBER_LENGTH);
+    }
+
+    /// The address that Extended frames must be sent to.
+    pub fn set_address(&mut self, address: u8) {
+        self.address = address;
+    }
+
+    pub fn set_version(&mut self, version: VersionInfo) {
+        self.version = version;
+    }
+
+    pub fn set_erg_machine_type(&mut self, erg_machine_type: ErgMachineType) {
+        self.erg_machine_type = erg_machine_type;
+    }
+
+    /// Time per 500 meters. Anything faster than `MIN_PACE` is rowed at `MIN_PACE`.
+    pub fn set_pace(&mut self, pace: Duration) {
+        self.pace = std::cmp::max(pace, MIN_PACE);
+    }
+
+    pub fn set_stroke_rate(&mut self, stroke_rate: u8) {
+        self.stroke_rate = stroke_rate;
+    }
+
+    /// Beats per minute, or 0 for no heart rate belt.
+    pub fn set_heart_rate(&mut self, heart_rate: u8) {
+        self.heart_rate = heart_rate;
+    }
+
+    /// The belt that the heart rate comes from. None is paired to begin with.
+    pub fn set_heart_rate_belt(&mut self, belt: HeartRateBelt) {
+        self.heart_rate_belt = belt;
+    }
+
+    pub fn set_drag_factor(&mut self, drag_factor: u8) {
+        self.drag_factor = drag_factor;
+    }
+
+    pub fn set_odometer(&mut self, meters: u32) {
+        self.odometer = f64::from(meters);
+    }
+
+    pub fn start_rowing(&mut self) {
+        self.rowing = true;
+    }
+
+    pub fn stop_rowing(&mut self) {
+        self.rowing = false;
+    }
+
+    pub fn state(&self) -> SlaveState {
+        self.state
+    }
+
+    pub fn workout_state(&self) -> WorkoutState {
+        self.workout_state
+    }
+
+    /// Lets simulated time pass.
+    pub fn advance(&mut self, mut d: Duration) {
+        while d > Duration::from_secs(0) {
+            let step = std::cmp::min(d, STEP);
+            self.step(step);
+            d -= step;
+        }
+    }
+
+    fn step(&mut self, dt: Duration) {
+        match self.workout_state {
+            WorkoutState::WorkoutEnd
+            | WorkoutState::Terminate
+            | WorkoutState::WorkoutLogged
+            | WorkoutState::Rearm => return,
+            WorkoutState::IntervalRest => {
+                self.rest_remaining = self.rest_remaining.checked_sub(dt).unwrap_or_default();
+                if self.rest_remaining == Duration::from_secs(0) {
+                    self.interval_count = self.interval_count.wrapping_add(1);
+                    self.interval = Progress::new();
+                    self.split = Progress::new();
+                    self.workout_state = self.work_state();
+                }
+                return;
+            }
+            WorkoutState::WaitToBegin => {
+                if !self.rowing {
+                    return;
+                }
+                if let SlaveState::Ready | SlaveState::Idle | SlaveState::HaveID = self.state {
+                    self.state = SlaveState::InUse;
+                }
+                self.workout_state = self.work_state();
+            }
+            _ => (),
+        }
+        if !self.rowing {
+            return;
+        }
+        let was_driving = self.stroke_state() == StrokeState::Driving;
+        let secs = dt.as_secs_f64();
+        let meters = secs * 500.0 / self.pace.as_secs_f64();
+        let calories = secs * self.calories_per_hour() / 3600.0;
+        let energy = secs * self.watts();
+        let strokes = secs * f64::from(self.stroke_rate) / 60.0;
+        for progress in [&mut self.interval, &mut self.total, &mut self.split].iter_mut() {
+            progress.time += dt;
+            progress.meters += meters;
+            progress.calories += calories;
+            progress.energy += energy;
+            progress.strokes += strokes;
+        }
+        self.odometer += meters;
+        self.heart_beats += secs * f64::from(self.heart_rate) / 60.0;
+        if self.stroke_state() == StrokeState::Driving {
+            // The last drive's curve is gone once the next one starts, read or not.
+            if !was_driving {
+                self.force_plot.clear();
+            }
+            let phase = self.total.strokes.fract() / DRIVE_FRACTION;
+            let peak = self.average_drive_force() * std::f64::consts::FRAC_PI_2;
+            self.force_plot
+                .push_back((peak * (std::f64::consts::PI * phase).sin()) as u16);
+        }
+
+        let (split_type, split_value) = self.split_duration;
+        if split_value > 0 && self.split.reached(split_type, split_value) {
+            self.last_split = (self.split.time, self.split.meters);
+            self.split = Progress::new();
+        }
+        let (duration_type, duration_value) = self.workout_duration;
+        if self.workout_type == WorkoutType::JustRowNoSplits
+            || self.workout_type == WorkoutType::JustRowSplits
+            || duration_value == 0
+            || !self.interval.reached(duration_type, duration_value)
+        {
+            return;
+        }
+        if self.is_interval_workout() && self.rest_duration > Duration::from_secs(0) {
+            self.workout_state = WorkoutState::IntervalRest;
+            self.rest_remaining = self.rest_duration;
+        } else if self.is_interval_workout() {
+            self.interval_count = self.interval_count.wrapping_add(1);
+            self.interval = Progress::new();
+        } else {
+            self.workout_state = WorkoutState::WorkoutEnd;
+            self.state = SlaveState::Finished;
+        }
+    }
+
+    fn is_interval_workout(&self) -> bool {
+        matches!(
+            self.workout_type,
+            WorkoutType::FixedTimeInterval
+                | WorkoutType::FixedDistanceInterval
+                | WorkoutType::FixedCalorieInterval
+                | WorkoutType::VariableInterval
+                | WorkoutType::VariableUndefinedRestInterval
+        )
+    }
+
+    /// The workout state for the work part of the current workout.
+    fn work_state(&self) -> WorkoutState {
+        if !self.is_interval_workout() {
+            return WorkoutState::WorkoutRow;
+        }
+        match self.workout_duration.0 {
+            DurationType::Time => WorkoutState::IntervalWorkTime,
+            _ => WorkoutState::IntervalWorkDistance,
+        }
+    }
+
+    /// Forgets everything that was set over CSAFE, like the `Reset` command does. The
+    /// machine itself - its identity, odometer, and the rower - stays the same.
+    fn reset(&mut self) {
+        *self = Simulator {
+            serial_number: std::mem::take(&mut self.serial_number),
+            version: self.version,
+            erg_machine_type: self.erg_machine_type,
+            address: self.address,
+            frame_toggle: self.frame_toggle,
+            responses: std::mem::take(&mut self.responses),
+            rowing: self.rowing,
+            pace: self.pace,
+            stroke_rate: self.stroke_rate,
+            heart_rate: self.heart_rate,
+            heart_rate_belt: self.heart_rate_belt,
+            drag_factor: self.drag_factor,
+            odometer: self.odometer,
+            ..Simulator::new()
+        };
+    }
+
+    /// Puts the workout back at the start, keeping what was programmed.
+    fn rearm(&mut self) {
+        self.workout_state = WorkoutState::WaitToBegin;
+        self.interval_count = 0;
+        self.interval = Progress::new();
+        self.total = Progress::new();
+        self.split = Progress::new();
+        self.rest_remaining = Duration::from_secs(0);
+        self.last_split = (Duration::from_secs(0), 0.0);
+        self.heart_beats = 0.0;
+        self.force_plot.clear();
+    }
+
+    fn watts(&self) -> f64 {
+        if self.rowing {
+            2.8 / (self.pace.as_secs_f64() / 500.0).powi(3)
+        } else {
+            0.0
+        }
+    }
+
+    /// The average force on the handle over a drive, in pounds.
+    fn average_drive_force(&self) -> f64 {
+        if !self.rowing || self.stroke_rate == 0 {
+            return 0.0;
+        }
+        let joules = self.watts() * 60.0 / f64::from(self.stroke_rate);
+        // Newtons, over a drive that's `STROKE_LENGTH` long.
+        joules / (f64::from(STROKE_LENGTH) / 100.0) * 0.224_809
+    }
+
+    fn calories_per_hour(&self) -> f64 {
+        if self.rowing {
+            self.watts() * 4.0 * 0.8604 + 300.0
+        } else {
+            0.0
+        }
+    }
+
+    fn stroke_state(&self) -> StrokeState {
+        if !self.rowing || self.stroke_rate == 0 {
+            return StrokeState::WaitingForWheelToReachMinSpeed;
+        }
+        if self.total.strokes.fract() < DRIVE_FRACTION {
+            StrokeState::Driving
+        } else {
+            StrokeState::Recovery
+        }
+    }
+
+    /// Average pace over some amount of work, in hundredths of a second per 500 meters.
+    fn average_pace(progress: &Progress) -> u32 {
+        if progress.meters > 0.0 {
+            (progress.time.as_secs_f64() * 500.0 / progress.meters * 100.0) as u32
+        } else {
+            0
+        }
+    }
+
+    fn average_watts(progress: &Progress) -> u32 {
+        if progress.time > Duration::from_secs(0) {
+            (progress.energy / progress.time.as_secs_f64()) as u32
+        } else {
+            0
+        }
+    }
+
+    fn average_stroke_rate(progress: &Progress) -> u8 {
+        if progress.time > Duration::from_secs(0) {
+            (progress.strokes * 60.0 / progress.time.as_secs_f64()) as u8
+        } else {
+            0
+        }
+    }
+
+    fn status(&self) -> u8 {
+        u8::from(FrameStatus {
+            frame_toggle: self.frame_toggle,
+            previous_frame: PreviousFrameStatus::Ok,
+            state: self.state,
+        })
+    }
+
+    /// Handles a public CSAFE command, returning its response data, or `None` if the
+    /// command isn't supported.
+    fn command(&mut self, identifier: u8, data: &[u8]) -> Option<Vec<u8>> {
+        let response = match (identifier, data) {
+            (csafe_commands::AUTO_UPLOAD, &[x]) => {
+                self.auto_upload = x;
+                vec![]
+            }
+            (csafe_commands::SET_ID_DIGITS, &[x]) => {
+                self.id_digits = x;
+                vec![]
+            }
+            (csafe_commands::SET_TIME, &[h, m, s]) => {
+                self.time = (h, m, s);
+                vec![]
+            }
+            (csafe_commands::SET_DATE, &[y, m, d]) => {
+                self.date = (y, m, d);
+                vec![]
+            }
+            (csafe_commands::SET_TIMEOUT, &[x]) => {
+                self.timeout = x;
+                vec![]
+            }
+            (csafe_commands::SET_TWORK, &[h, m, s]) => {
+                self.workout_type = WorkoutType::FixedTimeNoSplits;
+                self.workout_duration = (
+                    DurationType::Time,
+                    units::to_hundredths(units::from_hms(h, m, s)),
+                );
+                self.rearm();
+                vec![]
+            }
+            (csafe_commands::SET_HORIZONTAL, &[lo, hi, u]) => {
+                self.horizontal = (u16::from_le_bytes([lo, hi]), Units::from(u));
+                let meters = units::Distance::new(u32::from(self.horizontal.0), self.horizontal.1)
+                    .meters()
+                    .unwrap_or(0.0);
+                self.workout_type = WorkoutType::FixedDistanceNoSplits;
+                self.workout_duration = (DurationType::Distance, meters as u32);
+                self.rearm();
+                vec![]
+            }
+            (csafe_commands::SET_CALORIES, &[lo, hi]) => {
+                self.calories_goal = u16::from_le_bytes([lo, hi]);
+                self.workout_type = WorkoutType::FixedCalorie;
+                self.workout_duration = (DurationType::Calories, u32::from(self.calories_goal));
+                self.rearm();
+                vec![]
+            }
+            (csafe_commands::SET_PROGRAM, &[program, x]) => {
+                self.program = (program, x);
+                vec![]
+            }
+            (csafe_commands::SET_USER_INFO, &[lo, hi, u, age, gender]) => {
+                self.user_info = (u16::from_le_bytes([lo, hi]), Units::from(u), age, gender);
+                vec![]
+            }
+            (csafe_commands::SET_POWER, &[lo, hi, u]) => {
+                self.power_goal = (u16::from_le_bytes([lo, hi]), Units::from(u));
+                vec![]
+            }
+            (csafe_commands::GET_STATUS, &[]) => vec![self.status()],
+            (csafe_commands::RESET, &[]) => {
+                self.reset();
+                vec![]
+            }
+            (csafe_commands::GO_IDLE, &[]) => {
+                self.state = SlaveState::Idle;
+                vec![]
+            }
+            (csafe_commands::GO_HAVE_ID, &[]) => {
+                self.state = SlaveState::HaveID;
+                vec![]
+            }
+            (csafe_commands::GO_IN_USE, &[]) => {
+                self.state = SlaveState::InUse;
+                vec![]
+            }
+            (csafe_commands::GO_FINISHED, &[]) => {
+                self.state = SlaveState::Finished;
+                vec![]
+            }
+            (csafe_commands::GO_READY, &[]) => {
+                self.state = SlaveState::Ready;
+                vec![]
+            }
+            (csafe_commands::BAD_ID, &[]) => {
+                self.state = SlaveState::Idle;
+                vec![]
+            }
+            (csafe_commands::GET_VERSION, &[]) => {
+                let mut v = vec![
+                    self.version.manufacturer_id,
+                    self.version.class_id,
+                    self.version.model,
+                ];
+                v.extend_from_slice(&self.version.hardware_version.to_le_bytes());
+                v.extend_from_slice(&self.version.software_version.to_le_bytes());
+                v
+            }
+            (csafe_commands::GET_USER_ID, &[]) => self.user_id.clone().into_bytes(),
+            (csafe_commands::GET_UNITS, &[]) => vec![0],
+            (csafe_commands::GET_SERIAL_NUMBER, &[]) => self.serial_number.clone().into_bytes(),
+            (csafe_commands::GET_ODOMETER, &[]) => {
+                with_units(&(self.odometer as u32).to_le_bytes(), Units::Meter)
+            }
+            (csafe_commands::GET_ERROR_CODE, &[]) => vec![0, 0, 0],
+            (csafe_commands::GET_TWORK, &[]) => {
+                let (h, m, s) = units::to_hms(self.interval.time);
+                vec![h, m, s]
+            }
+            (csafe_commands::GET_HORIZONTAL, &[]) => {
+                with_units(&(self.interval.meters as u16).to_le_bytes(), Units::Meter)
+            }
+            (csafe_commands::GET_CALORIES, &[]) => {
+                (self.total.calories as u16).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_PROGRAM, &[]) => vec![self.program.0, self.program.1],
+            (csafe_commands::GET_PACE, &[]) => {
+                let secs_per_km = if self.rowing {
+                    (self.pace.as_secs_f64() * 2.0) as u16
+                } else {
+                    0
+                };
+                with_units(&secs_per_km.to_le_bytes(), Units::SecondsPerKilometer)
+            }
+            (csafe_commands::GET_CADENCE, &[]) => with_units(
+                &u16::from(self.current_stroke_rate()).to_le_bytes(),
+                Units::StrokesPerMinute,
+            ),
+            (csafe_commands::GET_USER_INFO, &[]) => {
+                let (weight, weight_units, age, gender) = self.user_info;
+                let mut v = with_units(&weight.to_le_bytes(), weight_units);
+                v.extend_from_slice(&[age, gender]);
+                v
+            }
+            (csafe_commands::GET_HEART_RATE, &[]) => vec![self.heart_rate],
+            (csafe_commands::GET_POWER, &[]) => {
+                with_units(&(self.watts() as u16).to_le_bytes(), Units::Watts)
+            }
+            (csafe_commands::PROPRIETARY_COMMAND, data) => {
+                self.wrapped(data, Simulator::proprietary)?
+            }
+            (csafe_commands::PROPRIETARY_CONFIG_COMMAND, data) => {
+                self.wrapped(data, Simulator::config)?
+            }
+            _ => return None,
+        };
+        Some(response)
+    }
+
+    /// Handles every command nested inside of a wrapper, returning their responses
+    /// concatenated together.
+    fn wrapped(
+        &mut self,
+        data: &[u8],
+        handler: fn(&mut Simulator, u8, &[u8]) -> Option<Vec<u8>>,
+    ) -> Option<Vec<u8>> {
+        let mut result = vec![];
+        for (identifier, data) in split_commands(data)? {
+            let response = handler(self, identifier, data)?;
+            result.push(identifier);
+            result.push(response.len() as u8);
+            result.extend(response);
+        }
+        Some(result)
+    }
+
+    fn current_stroke_rate(&self) -> u8 {
+        if self.rowing {
+            self.stroke_rate
+        } else {
+            0
+        }
+    }
+
+    fn proprietary(&mut self, identifier: u8, data: &[u8]) -> Option<Vec<u8>> {
+        let current_pace = if self.rowing {
+            units::to_hundredths(self.pace)
+        } else {
+            0
+        };
+        let response = match (identifier, data) {
+            (csafe_commands::GET_WORK_TIME, &[]) => {
+                with_byte(&units::to_hundredths(self.interval.time).to_le_bytes(), 0)
+            }
+            (csafe_commands::GET_WORK_DISTANCE, &[]) => {
+                with_byte(&((self.interval.meters * 10.0) as u32).to_le_bytes(), 0)
+            }
+            (csafe_commands::GET_WORKOUT_TYPE, &[]) => vec![u8::from(self.workout_type)],
+            (csafe_commands::GET_STROKE_STATS, &[0]) => self.stroke_stats(),
+            (csafe_commands::GET_FORCE_PLOT_DATA, &[bytes]) => self.force_plot_data(bytes),
+            (csafe_commands::GET_EXTENDED_HR_BELT_INFO, &[0]) => {
+                let belt = self.heart_rate_belt;
+                let mut v = vec![belt.manufacturer_id, belt.device_type];
+                v.extend_from_slice(&belt.belt_id.to_le_bytes());
+                v
+            }
+            (csafe_commands::GET_DISPLAY_TYPE, &[]) => vec![0],
+            (csafe_commands::GET_DISPLAY_UNITS, &[]) => vec![0],
+            (csafe_commands::GET_WORKOUT_STATE, &[]) => vec![u8::from(self.workout_state)],
+            (csafe_commands::GET_INTERVAL_TYPE, &[]) => vec![u8::from(self.interval_type)],
+            (csafe_commands::GET_OPERATIONAL_STATE, &[]) => {
+                let state = match self.state {
+                    SlaveState::InUse | SlaveState::Finished => OperationalState::Workout,
+                    _ => OperationalState::Ready,
+                };
+                vec![u8::from(state)]
+            }
+            (csafe_commands::GET_ROWING_STATE, &[]) => {
+                let state = match (self.rowing, self.workout_state) {
+                    (true, WorkoutState::WorkoutRow)
+                    | (true, WorkoutState::IntervalWorkTime)
+                    | (true, WorkoutState::IntervalWorkDistance) => RowingState::Active,
+                    _ => RowingState::Inactive,
+                };
+                vec![u8::from(state)]
+            }
+            (csafe_commands::GET_BATTERY_LEVEL, &[]) => vec![100],
+            (csafe_commands::GET_WORKOUT_INTERVAL_COUNT, &[]) => vec![self.interval_count],
+            (csafe_commands::GET_STROKE_PACE, &[]) => current_pace.to_le_bytes().to_vec(),
+            (csafe_commands::GET_STROKE_POWER, &[]) => (self.watts() as u32).to_le_bytes().to_vec(),
+            (csafe_commands::GET_STROKE_CALORIC_BURN_RATE, &[]) => {
+                (self.calories_per_hour() as u32).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_SPLIT_AVG_PACE, &[]) => {
+                Simulator::average_pace(&self.split).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_SPLIT_AVG_POWER, &[]) => {
+                Simulator::average_watts(&self.split).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_TOTAL_AVG_PACE, &[]) => {
+                Simulator::average_pace(&self.total).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_TOTAL_AVG_POWER, &[]) => {
+                Simulator::average_watts(&self.total).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_TOTAL_AVG_CALORIES, &[]) => {
+                let per_hour = if self.total.time > Duration::from_secs(0) {
+                    self.total.calories * 3600.0 / self.total.time.as_secs_f64()
+                } else {
+                    0.0
+                };
+                (per_hour as u32).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_STROKE_RATE, &[]) => vec![self.current_stroke_rate()],
+            (csafe_commands::GET_SPLIT_AVG_STROKE_RATE, &[]) => {
+                vec![Simulator::average_stroke_rate(&self.split)]
+            }
+            (csafe_commands::GET_TOTAL_AVG_STROKE_RATE, &[]) => {
+                vec![Simulator::average_stroke_rate(&self.total)]
+            }
+            (csafe_commands::GET_AVG_HEART_RATE, &[]) => {
+                let average = if self.total.time > Duration::from_secs(0) {
+                    self.heart_beats * 60.0 / self.total.time.as_secs_f64()
+                } else {
+                    0.0
+                };
+                vec![average.round() as u8]
+            }
+            (csafe_commands::GET_LAST_SPLIT_TIME, &[]) => units::to_hundredths(self.last_split.0)
+                .to_le_bytes()
+                .to_vec(),
+            (csafe_commands::GET_LAST_SPLIT_DISTANCE, &[]) => {
+                (self.last_split.1 as u32).to_le_bytes().to_vec()
+            }
+            (csafe_commands::GET_TARGET_PACE_TIME, &[]) => units::to_hundredths(self.target_pace)
+                .to_le_bytes()
+                .to_vec(),
+            (csafe_commands::GET_STROKE_STATE, &[]) => vec![u8::from(self.stroke_state())],
+            (csafe_commands::GET_DRAG_FACTOR, &[]) => vec![self.drag_factor],
+            (csafe_commands::GET_ERG_MACHINE_TYPE, &[]) => vec![u8::from(self.erg_machine_type)],
+            (csafe_commands::GET_ERROR_TYPE, &[]) => vec![0],
+            (csafe_commands::GET_ERROR_VALUE, &[]) => vec![0, 0],
+            (csafe_commands::GET_REST_TIME, &[]) => {
+                let secs = std::cmp::min(self.rest_remaining.as_secs(), u64::from(u16::MAX));
+                (secs as u16).to_le_bytes().to_vec()
+            }
+            _ => return None,
+        };
+        Some(response)
+    }
+
+    /// Stroke statistics for the most recent stroke, all of which look the same when
+    /// rowing at a constant pace.
+    fn stroke_stats(&self) -> Vec<u8> {
+        if !self.rowing || self.stroke_rate == 0 {
+            return vec![0; 16];
+        }
+        let period = 60.0 / f64::from(self.stroke_rate);
+        let stroke_meters = period * 500.0 / self.pace.as_secs_f64();
+        let joules = self.watts() * period;
+        // Reported in tenths of a pound.
+        let avg_force = self.average_drive_force() * 10.0;
+        let mut v = vec![];
+        v.extend_from_slice(&((stroke_meters * 100.0) as u16).to_le_bytes());
+        v.push((period * DRIVE_FRACTION * 100.0) as u8);
+        v.extend_from_slice(&((period * (1.0 - DRIVE_FRACTION) * 100.0) as u16).to_le_bytes());
+        v.push(STROKE_LENGTH);
+        v.extend_from_slice(&(self.total.strokes as u16).to_le_bytes());
+        v.extend_from_slice(&((avg_force * 1.8) as u16).to_le_bytes());
+        v.extend_from_slice(&((avg_force * period * DRIVE_FRACTION) as u16).to_le_bytes());
+        v.extend_from_slice(&(avg_force as u16).to_le_bytes());
+        v.extend_from_slice(&((joules * 10.0) as u16).to_le_bytes());
+        v
+    }
+
+    /// Takes up to `bytes` bytes of samples off the front of the force curve. The block
+    /// is always 32 bytes long, after the count of the bytes that are real.
+    fn force_plot_data(&mut self, bytes: u8) -> Vec<u8> {
+        let count = std::cmp::min(
+            usize::from(std::cmp::min(bytes, 32)) / 2,
+            self.force_plot.len(),
+        );
+        let mut v = vec![(count * 2) as u8];
+        for sample in self.force_plot.drain(..count) {
+            v.extend_from_slice(&sample.to_le_bytes());
+        }
+        v.resize(33, 0);
+        v
+    }
+
+    fn config(&mut self, identifier: u8, data: &[u8]) -> Option<Vec<u8>> {
+        match (identifier, data) {
+            (csafe_commands::SET_WORKOUT_TYPE, &[x]) => {
+                self.workout_type = WorkoutType::from(x);
+                if let WorkoutType::JustRowNoSplits | WorkoutType::JustRowSplits = self.workout_type
+                {
+                    self.workout_duration = (DurationType::Time, 0);
+                }
+            }
+            (csafe_commands::SET_WORKOUT_DURATION, &[t, a, b, c, d]) => {
+                self.workout_duration = (DurationType::from(t), u32::from_be_bytes([a, b, c, d]));
+            }
+            (csafe_commands::SET_REST_DURATION, &[a, b]) => {
+                self.rest_duration = Duration::from_secs(u64::from(u16::from_be_bytes([a, b])));
+            }
+            (csafe_commands::SET_SPLIT_DURATION, &[t, a, b, c, d]) => {
+                self.split_duration = (DurationType::from(t), u32::from_be_bytes([a, b, c, d]));
+            }
+            (csafe_commands::SET_TARGET_PACE_TIME, &[a, b, c, d]) => {
+                self.target_pace = units::from_hundredths(u32::from_be_bytes([a, b, c, d]));
+            }
+            (csafe_commands::SET_SCREEN_STATE, &[screen_type, value]) => {
+                self.screen = (ScreenType::from(screen_type), value);
+                if ScreenType::from(screen_type) == ScreenType::Workout {
+                    match WorkoutScreen::from(value) {
+                        WorkoutScreen::PrepareToRowWorkout | WorkoutScreen::RearmWorkout => {
+                            self.rearm();
+                            self.state = SlaveState::InUse;
+                        }
+                        WorkoutScreen::TerminateWorkout => {
+                            self.workout_state = WorkoutState::Terminate;
+                            self.state = SlaveState::Finished;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            (csafe_commands::CONFIGURE_WORKOUT, &[x]) => self.configuring = x != 0,
+            (csafe_commands::SET_INTERVAL_TYPE, &[x]) => self.interval_type = IntervalType::from(x),
+            (csafe_commands::SET_WORKOUT_INTERVAL_COUNT, &[x]) => self.interval_count = x,
+            _ => return None,
+        }
+        Some(vec![])
+    }
+
+    /// Answers a whole frame, returning the response frame that the PM would send back, or
+    /// `None` if the frame was addressed to some other machine.
+    fn respond(&mut self, frame: &[u8]) -> Option<Vec<u8>> {
+        let (address, body) = match unstuff(frame) {
+            Some(unstuffed) => unstuffed,
+            None => {
+                self.frame_toggle = !self.frame_toggle;
+                return Some(self.bare_frame(None, PreviousFrameStatus::Bad));
+            }
+        };
+        let address = match address {
+            Some(a)
+                if a.destination == self.address
+                    || a.destination == consts::CSAFE_BROADCAST_ADDRESS =>
+            {
+                Some(FrameAddress {
+                    destination: a.source,
+                    source: self.address,
+                })
+            }
+            Some(_) => return None,
+            None => None,
+        };
+        self.frame_toggle = !self.frame_toggle;
+        let commands = match split_commands(&body) {
+            Some(commands) => commands
+                .into_iter()
+                .map(|(identifier, data)| (identifier, data.to_vec()))
+                .collect::<Vec<(u8, Vec<u8>)>>(),
+            None => return Some(self.bare_frame(address, PreviousFrameStatus::Bad)),
+        };
+        let mut body = vec![self.status()];
+        for (identifier, data) in commands {
+            match self.command(identifier, &data) {
+                Some(response) => {
+                    body.push(identifier);
+                    body.push(response.len() as u8);
+                    body.extend(response);
+                }
+                None => return Some(self.bare_frame(address, PreviousFrameStatus::Rejected)),
+            }
+        }
+        // Commands like `GoInUse` change the state, which the status byte should reflect.
+        body[0] = self.status();
+        Some(encode_frame(address, &body))
+    }
+
+    /// A frame with only a status byte.
+    fn bare_frame(
+        &self,
+        address: Option<FrameAddress>,
+        previous_frame: PreviousFrameStatus,
+    ) -> Vec<u8> {
+        encode_frame(
+            address,
+            &[u8::from(FrameStatus {
+                frame_toggle: self.frame_toggle,
+                previous_frame,
+                state: self.state,
+            })],
+        )
+    }
+}
+
+impl Transport for Simulator {
+    type Error = Infallible;
+
+    fn discard(&mut self) -> Result<(), Infallible> {
+        self.responses.clear();
+        Ok(())
+    }
+
+    fn send(&mut self, frame: &[u8]) -> Result<(), Infallible> {
+        if let Some(response) = self.respond(frame) {
+            self.responses.push_back(response);
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, _timeout: Duration) -> Result<Option<Vec<u8>>, Infallible> {
+        Ok(self.responses.pop_front())
+    }
+}
+
+fn with_units(v: &[u8], units: Units) -> Vec<u8> {
+    with_byte(v, u8::from(units))
+}
+
+fn with_byte(v: &[u8], b: u8) -> Vec<u8> {
+    v.iter().copied().chain(std::iter::once(b)).collect()
+}
+
+/// Checks a command frame's flags, stuffing, and checksum, and returns its addresses, if
+/// it's an Extended frame, and its contents without the checksum.
+fn unstuff(frame: &[u8]) -> Option<(Option<FrameAddress>, Vec<u8>)> {
+    let (&start, rest) = frame.split_first()?;
+    let (&stop, stuffed) = rest.split_last()?;
+    let extended = match start {
+        consts::CSAFE_START_FLAG => false,
+        consts::CSAFE_EXTENDED_START_FLAG => true,
+        _ => return None,
+    };
+    if stop != consts::CSAFE_STOP_FLAG {
+        return None;
+    }
+    let mut body = vec![];
+    let mut iter = stuffed.iter();
+    while let Some(&x) = iter.next() {
+        match x {
+            0xf3 => match iter.next() {
+                Some(&y) if y <= 0x03 => body.push(0xf0 + y),
+                _ => return None,
+            },
+            0xf0..=0xf2 => return None,
+            x => body.push(x),
+        }
+    }
+    let address = if extended {
+        if body.len() < 2 {
+            return None;
+        }
+        let address: Vec<u8> = body.drain(..2).collect();
+        Some(FrameAddress {
+            destination: address[0],
+            source: address[1],
+        })
+    } else {
+        None
+    };
+    let checksum = body.pop()?;
+    if body.iter().fold(0, |acc, x| acc ^ x) == checksum {
+        Some((address, body))
+    } else {
+        None
+    }
+}
+
+/// Splits commands into identifiers and data. Identifiers below `0x80` are long
+/// commands, which are followed by a byte count and their data; the rest are short
+/// commands, with no data. This holds inside of the wrappers, too.
+fn split_commands(v: &[u8]) -> Option<Vec<(u8, &[u8])>> {
+    let mut result = vec![];
+    let mut i = 0;
+    while i < v.len() {
+        let identifier = v[i];
+        if identifier >= 0x80 {
+            result.push((identifier, &v[i..i]));
+            i += 1;
+        } else {
+            let bytes = usize::from(*v.get(i + 1)?);
+            result.push((identifier, v.get(i + 2..i + 2 + bytes)?));
+            i += 2 + bytes;
+        }
+    }
+    Some(result)
+}
+
+/// Wraps a status byte and responses in flags, addresses, and a checksum, stuffing as
+/// necessary.
+fn encode_frame(address: Option<FrameAddress>, body: &[u8]) -> Vec<u8> {
+    let checksum = body.iter().fold(0, |acc, x| acc ^ x);
+    let (start_flag, address) = match address {
+        Some(a) => (
+            consts::CSAFE_EXTENDED_START_FLAG,
+            vec![a.destination, a.source],
+        ),
+        None => (consts::CSAFE_START_FLAG, vec![]),
+    };
+    std::iter::once(start_flag)
+        .chain(
+            address
+                .into_iter()
+                .chain(body.iter().copied())
+                .chain(std::iter::once(checksum))
+                .flat_map(csafe::stuff_bytes),
+        )
+        .chain(std::iter::once(consts::CSAFE_STOP_FLAG))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_identity() {
+        use crate::concept2command::Concept2Command;
+        use crate::concept2response::Concept2Response;
+        let mut sim = super::Simulator::new();
+        let frame = crate::transport::round_trip(
+            &mut sim,
+            &crate::csafe::CSAFEFrame::new(vec![
+                Concept2Command::GetSerialNumber,
+                Concept2Command::GetVersion,
+                Concept2Command::GetUserID,
+            ]),
+            crate::transport::DEFAULT_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(crate::status::SlaveState::Ready, frame.status.state);
+        assert_eq!(
+            Concept2Response::GetSerialNumber(String::from("430000000")),
+            frame.responses[0]
+        );
+        match &frame.responses[1] {
+            Concept2Response::GetVersion(v) => {
+                assert_eq!(crate::version::PMModel::PM5, v.pm_model())
+            }
+            r => panic!("unexpected response {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_normalised_settings() {
+        let mut sim = super::Simulator::new();
+        sim.set_serial_number("12345");
+        assert_eq!("000012345", sim.serial_number);
+        sim.set_serial_number("4300000012345");
+        assert_eq!("430000001", sim.serial_number);
+        sim.set_pace(crate::units::Duration::from_secs(0));
+        assert_eq!(super::MIN_PACE, sim.pace);
+    }
+
+    #[test]
+    fn test_stuffed_response() {
+        use crate::concept2command::Concept2Command;
+        use crate::concept2response::Concept2Response;
+        let mut sim = super::Simulator::new();
+        // 0x00f2f1f0 is made entirely of bytes that need to be stuffed.
+        sim.set_odometer(0x00f2_f1f0);
+        let cmd = crate::csafe::CSAFEFrame::new(vec![Concept2Command::GetOdometer]);
+        crate::transport::Transport::send(&mut sim, &cmd.to_vec()).unwrap();
+        let raw = crate::transport::Transport::receive(&mut sim, crate::transport::DEFAULT_TIMEOUT)
+            .unwrap()
+            .unwrap();
+        assert!(raw.contains(&0xf3));
+        assert_eq!(
+            Ok(vec![Concept2Response::GetOdometer(
+                crate::units::Distance::from_meters(0x00f2_f1f0)
+            )]),
+            crate::concept2response::parse_frame_at(&raw, 0).map(|f| f.responses)
+        );
+    }
+
+    #[test]
+    fn test_extended_frames() {
+        use crate::concept2command::Concept2Command;
+        let mut sim = super::Simulator::new();
+        sim.set_address(0x02);
+        let frame = crate::transport::round_trip(
+            &mut sim,
+            &crate::csafe::CSAFEFrame::extended(0x02, vec![Concept2Command::GetSerialNumber]),
+            crate::transport::DEFAULT_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(crate::csafe::FrameAddress {
+                destination: crate::consts::CSAFE_HOST_ADDRESS,
+                source: 0x02,
+            }),
+            frame.address
+        );
+        // Another machine's frame goes unanswered.
+        assert!(matches!(
+            crate::transport::round_trip(
+                &mut sim,
+                &crate::csafe::CSAFEFrame::extended(0x03, vec![Concept2Command::GetStatus]),
+                crate::transport::DEFAULT_TIMEOUT,
+            ),
+            Err(crate::error::Concept2Error::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_bad_frames() {
+        let mut sim = super::Simulator::new();
+        // Bad checksum.
+        crate::transport::Transport::send(&mut sim, &[0xf1, 0x80, 0x81, 0xf2]).unwrap();
+        // Unknown command.
+        crate::transport::Transport::send(&mut sim, &[0xf1, 0xfe, 0xfe, 0xf2]).unwrap();
+        let statuses: Vec<crate::status::PreviousFrameStatus> = (0..2)
+            .map(|_| {
+                let raw = crate::transport::Transport::receive(
+                    &mut sim,
+                    crate::transport::DEFAULT_TIMEOUT,
+                )
+                .unwrap()
+                .unwrap();
+                crate::concept2response::parse_frame_at(&raw, 0)
+                    .unwrap()
+                    .status
+                    .previous_frame
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                crate::status::PreviousFrameStatus::Bad,
+                crate::status::PreviousFrameStatus::Rejected
+            ],
+            statuses
+        );
+    }
+
+    #[test]
+    fn test_fixed_distance_workout() {
+        use crate::concept2command::{
+            Concept2Command, Concept2ProprietaryCommand, Concept2ProprietaryConfigCommand,
+        };
+        use crate::concept2response::{Concept2Response, Concept2ResponseProprietary};
+        use crate::units::Duration;
+        use crate::workout::{DurationType, ScreenType, WorkoutScreen, WorkoutState, WorkoutType};
+        let mut sim = super::Simulator::new();
+        sim.set_pace(Duration::from_secs(100));
+        crate::transport::round_trip(
+            &mut sim,
+            &crate::csafe::CSAFEFrame::new(vec![Concept2Command::ProprietaryConfigCommand(vec![
+                Concept2ProprietaryConfigCommand::SetWorkoutType(
+                    WorkoutType::FixedDistanceNoSplits,
+                ),
+                Concept2ProprietaryConfigCommand::SetWorkoutDuration(DurationType::Distance, 1000),
+                Concept2ProprietaryConfigCommand::ConfigureWorkout(true),
+                Concept2ProprietaryConfigCommand::SetScreenState(
+                    ScreenType::Workout,
+                    u8::from(WorkoutScreen::PrepareToRowWorkout),
+                ),
+            ])]),
+            crate::transport::DEFAULT_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(WorkoutState::WaitToBegin, sim.workout_state());
+
+        sim.start_rowing();
+        sim.advance(Duration::from_secs(100));
+        let query = crate::csafe::CSAFEFrame::new(vec![Concept2Command::ProprietaryCommand(vec![
+            Concept2ProprietaryCommand::GetWorkTime,
+            Concept2ProprietaryCommand::GetWorkDistance,
+            Concept2ProprietaryCommand::GetWorkoutState,
+            Concept2ProprietaryCommand::GetStrokePace,
+        ])]);
+        let frame =
+            crate::transport::round_trip(&mut sim, &query, crate::transport::DEFAULT_TIMEOUT)
+                .unwrap();
+        assert_eq!(
+            vec![Concept2Response::ProprietaryCommand(vec![
+                Concept2ResponseProprietary::GetWorkTime(Duration::from_secs(100)),
+                Concept2ResponseProprietary::GetWorkDistance(crate::units::Distance::new(
+                    5000,
+                    crate::units::Units::TenthMeter
+                )),
+                Concept2ResponseProprietary::GetWorkoutState(WorkoutState::WorkoutRow),
+                Concept2ResponseProprietary::GetStrokePace(Duration::from_secs(100)),
+            ])],
+            frame.responses
+        );
+
+        sim.advance(Duration::from_secs(150));
+        let frame =
+            crate::transport::round_trip(&mut sim, &query, crate::transport::DEFAULT_TIMEOUT)
+                .unwrap();
+        assert_eq!(crate::status::SlaveState::Finished, frame.status.state);
+        match &frame.responses[0] {
+            Concept2Response::ProprietaryCommand(r) => {
+                assert_eq!(
+                    Concept2ResponseProprietary::GetWorkoutState(WorkoutState::WorkoutEnd),
+                    r[2]
+                );
+                assert_eq!(
+                    Concept2ResponseProprietary::GetWorkTime(Duration::from_secs(200)),
+                    r[0]
+                );
+            }
+            r => panic!("unexpected response {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Decoding of the status byte that starts every response frame.
+//!
+//! The low nibble is the CSAFE slave state - where the machine is in the CSAFE state
+//! machine. Bits 4 and 5 report whether the *previous* frame that we sent was accepted,
+//! and bit 7 toggles on every new frame, so a repeated toggle value means that the
+//! machine is re-sending an old response.
+
+byte_enum! {
+    /// The CSAFE state machine's states, as reported by the machine.
+    pub enum SlaveState {
+        Error = 0,
+        Ready = 1,
+        Idle = 2,
+        HaveID = 3,
+        InUse = 5,
+        Paused = 6,
+        Finished = 7,
+        Manual = 8,
+        Offline = 9,
+    }
+}
+
+/// What happened to the last frame that was sent to the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviousFrameStatus {
+    Ok,
+    Rejected,
+    Bad,
+    NotReady,
+}
+
+/// The decoded status byte of a response frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStatus {
+    pub frame_toggle: bool,
+    pub previous_frame: PreviousFrameStatus,
+    pub state: SlaveState,
+}
+
+const STATE_MASK: u8 = 0x0f;
+const PREVIOUS_FRAME_MASK: u8 = 0x30;
+const PREVIOUS_FRAME_SHIFT: u8 = 4;
+const FRAME_TOGGLE_MASK: u8 = 0x80;
+
+impl From<u8> for FrameStatus {
+    fn from(b: u8) -> Self {
+        FrameStatus {
+            frame_toggle: b & FRAME_TOGGLE_MASK != 0,
+            previous_frame: match (b & PREVIOUS_FRAME_MASK) >> PREVIOUS_FRAME_SHIFT {
+                0 => PreviousFrameStatus::Ok,
+                1 => PreviousFrameStatus::Rejected,
+                2 => PreviousFrameStatus::Bad,
+                _ => PreviousFrameStatus::NotReady,
+            },
+            state: SlaveState::from(b & STATE_MASK),
+        }
+    }
+}
+
+impl From<FrameStatus> for u8 {
+    fn from(s: FrameStatus) -> Self {
+        let previous_frame: u8 = match s.previous_frame {
+            PreviousFrameStatus::Ok => 0,
+            PreviousFrameStatus::Rejected => 1,
+            PreviousFrameStatus::Bad => 2,
+            PreviousFrameStatus::NotReady => 3,
+        };
+        (if s.frame_toggle { FRAME_TOGGLE_MASK } else { 0 })
+            | (previous_frame << PREVIOUS_FRAME_SHIFT)
+            | (u8::from(s.state) & STATE_MASK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_frame_status() {
+        let status = super::FrameStatus::from(0x81);
+        assert_eq!(
+            super::FrameStatus {
+                frame_toggle: true,
+                previous_frame: super::PreviousFrameStatus::Ok,
+                state: super::SlaveState::Ready,
+            },
+            status
+        );
+        assert_eq!(0x81, u8::from(status));
+        assert_eq!(
+            super::PreviousFrameStatus::Bad,
+            super::FrameStatus::from(0x25).previous_frame
+        );
+        assert_eq!(
+            super::SlaveState::InUse,
+            super::FrameStatus::from(0x25).state
+        );
+    }
+}
@@ -0,0 +1,182 @@
+/// Turning polled stroke data into one event per stroke.
+///
+/// The PM keeps the statistics of the most recent stroke in `GetStrokeStats`, and says
+/// where the rower is in the current stroke with `GetStrokeState`. A stroke's numbers
+/// are settled once its drive has ended, so a `StrokeDetector` reports a stroke the first
+/// time that it sees the rower past the drive, as long as it has seen the drive itself or
+/// the stroke count has moved since the last report. A poll that's late, early, or
+/// repeated can't report a stroke twice; a drive that no poll landed in still moves the
+/// count; and a new workout whose count starts over at the last reported number still
+/// has its drive seen. Strokes that begin and end between two polls are missed, and show
+/// up as a gap in `stroke_count`. A stroke that was already over when the detector was
+/// created isn't reported.
+use crate::command;
+use crate::concept2response::StrokeStats;
+use crate::error::Concept2Error;
+use crate::monitor::PerformanceMonitor;
+use crate::transport::Transport;
+use crate::units::{self, Distance, Duration, Measurement, Units};
+use crate::workout::StrokeState;
+
+/// One completed stroke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrokeEvent {
+    pub drive_time: Duration,
+    pub recovery_time: Duration,
+    /// How far the handle travelled during the drive.
+    pub drive_length: Distance,
+    pub peak_force: Measurement,
+    pub average_force: Measurement,
+    /// Tenths of a joule.
+    pub work_per_stroke: u16,
+    /// The PM's count of strokes in this workout.
+    pub stroke_count: u16,
+}
+
+impl From<&StrokeStats> for StrokeEvent {
+    fn from(stats: &StrokeStats) -> StrokeEvent {
+        StrokeEvent {
+            drive_time: units::from_hundredths(u32::from(stats.drive_time)),
+            recovery_time: units::from_hundredths(u32::from(stats.recovery_time)),
+            drive_length: Distance::new(u32::from(stats.stroke_length), Units::Centimeter),
+            peak_force: Measurement::new(u32::from(stats.peak_drive_force), Units::TenthPounds),
+            average_force: Measurement::new(u32::from(stats.avg_drive_force), Units::TenthPounds),
+            work_per_stroke: stats.work_per_stroke,
+            stroke_count: stats.drive_count,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StrokeDetector {
+    /// The stroke count at the last reading past the drive.
+    last_count: Option<u16>,
+    /// Whether a reading has been in the drive since then.
+    seen_drive: bool,
+}
+
+impl StrokeDetector {
+    pub fn new() -> StrokeDetector {
+        StrokeDetector::default()
+    }
+
+    /// Takes one reading of the stroke state and stats, and returns the stroke that it
+    /// completes, if any. A stroke count that goes backwards, or starts over at the same
+    /// number, is a new workout, and its strokes are reported as usual.
+    pub fn update(&mut self, state: StrokeState, stats: &StrokeStats) -> Option<StrokeEvent> {
+        match state {
+            StrokeState::Driving => {
+                self.seen_drive = true;
+                return None;
+            }
+            StrokeState::DwellingAfterDrive | StrokeState::Recovery => (),
+            _ => return None,
+        }
+        let counted = self
+            .last_count
+            .is_some_and(|count| count != stats.drive_count);
+        let completed = self.seen_drive || counted;
+        self.last_count = Some(stats.drive_count);
+        self.seen_drive = false;
+        if completed {
+            Some(StrokeEvent::from(stats))
+        } else {
+            None
+        }
+    }
+
+    /// Reads the stroke state and stats in one frame, and passes them to `update`.
+    pub fn poll<T: Transport>(
+        &mut self,
+        pm: &mut PerformanceMonitor<T>,
+    ) -> Result<Option<StrokeEvent>, Concept2Error<T::Error>> {
+        let (state, stats) = pm.execute((command::GetStrokeState, command::GetStrokeStats))?;
+        Ok(self.update(state, &stats))
+    }
+
+    /// Forgets everything that's been read, as if the detector were new.
+    pub fn reset(&mut self) {
+        self.last_count = None;
+        self.seen_drive = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn stats(drive_count: u16) -> crate::concept2response::StrokeStats {
+        crate::concept2response::StrokeStats {
+            stroke_distance: 1000,
+            drive_time: 80,
+            recovery_time: 170,
+            stroke_length: 140,
+            drive_count,
+            peak_drive_force: 1800,
+            impulse_drive_force: 800,
+            avg_drive_force: 1000,
+            work_per_stroke: 6000,
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        use crate::workout::StrokeState;
+        let mut detector = super::StrokeDetector::new();
+        let readings = vec![
+            (StrokeState::Driving, 0),
+            (StrokeState::Recovery, 0),
+            (StrokeState::Recovery, 0),
+            // The next drive was missed entirely.
+            (StrokeState::Recovery, 1),
+            (StrokeState::Driving, 2),
+            (StrokeState::DwellingAfterDrive, 2),
+            (StrokeState::Recovery, 2),
+            // A new workout.
+            (StrokeState::Recovery, 0),
+            // Another new workout, with its first stroke counted the same as the last.
+            (StrokeState::Driving, 0),
+            (StrokeState::Recovery, 0),
+        ];
+        let events: Vec<u16> = readings
+            .into_iter()
+            .filter_map(|(state, count)| detector.update(state, &stats(count)))
+            .map(|e| e.stroke_count)
+            .collect();
+        assert_eq!(vec![0, 1, 2, 0, 0], events);
+
+        // A stroke that was over before the detector started isn't reported.
+        let mut detector = super::StrokeDetector::new();
+        assert_eq!(None, detector.update(StrokeState::Recovery, &stats(7)));
+        assert_eq!(None, detector.update(StrokeState::Driving, &stats(8)));
+        assert_eq!(
+            Some(8),
+            detector
+                .update(StrokeState::Recovery, &stats(8))
+                .map(|e| e.stroke_count)
+        );
+        assert_eq!(
+            crate::units::Duration::from_millis(800),
+            super::StrokeEvent::from(&stats(0)).drive_time
+        );
+    }
+
+    #[test]
+    fn test_jittery_polling() {
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_stroke_rate(24);
+        simulator.start_rowing();
+        let mut pm = crate::monitor::PerformanceMonitor::new(simulator);
+        let mut detector = super::StrokeDetector::new();
+        let mut events = vec![];
+        // Just under ten seconds at 24 strokes per minute, polled every 70 or 230
+        // milliseconds.
+        for i in 0..66 {
+            let step = if i % 2 == 0 { 70 } else { 230 };
+            pm.transport_mut()
+                .advance(crate::units::Duration::from_millis(step));
+            if let Some(event) = detector.poll(&mut pm).unwrap() {
+                events.push(event.stroke_count);
+            }
+        }
+        assert_eq!(vec![0, 1, 2, 3], events);
+    }
+}
@@ -0,0 +1,142 @@
+/// The link between this library and a Concept2.
+///
+/// Everything above this module deals in complete CSAFE frames - start flag through stop
+/// flag, already stuffed. A `Transport` is responsible for getting those bytes to the
+/// machine and back, however it is connected: USB HID (see `hid_csafe::HidTransport`),
+/// serial, Bluetooth, a TCP bridge, or an in-memory fake for tests.
+use std::time::Duration;
+
+use crate::concept2response::{self, CSAFEResponseFrame};
+use crate::csafe::CSAFEFrame;
+use crate::error::Concept2Error;
+
+/// How long `round_trip` waits for a response unless told otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+pub trait Transport {
+    type Error;
+
+    /// Throws away anything that has arrived but hasn't been received, such as the late
+    /// response to a request that timed out, so that the next `receive` answers the next
+    /// `send`. Must not wait for anything.
+    fn discard(&mut self) -> Result<(), Self::Error>;
+
+    /// Sends one complete CSAFE frame.
+    fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error>;
+
+    /// Waits up to `timeout` for one complete CSAFE frame from the machine. Any
+    /// link-level framing (such as a HID report number) must be stripped, so that the
+    /// first byte returned is the start flag. Returns `Ok(None)` on timeout.
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, Self::Error>;
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    type Error = T::Error;
+
+    fn discard(&mut self) -> Result<(), Self::Error> {
+        (**self).discard()
+    }
+
+    fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error> {
+        (**self).send(frame)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, Self::Error> {
+        (**self).receive(timeout)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    type Error = T::Error;
+
+    fn discard(&mut self) -> Result<(), Self::Error> {
+        (**self).discard()
+    }
+
+    fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error> {
+        (**self).send(frame)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, Self::Error> {
+        (**self).receive(timeout)
+    }
+}
+
+/// Sends `cmd` and parses the response. Anything left over from an earlier request is
+/// discarded first. Error offsets index the response frame itself, starting at its start
+/// flag.
+pub fn round_trip<T: Transport + ?Sized>(
+    transport: &mut T,
+    cmd: &CSAFEFrame,
+    timeout: Duration,
+) -> Result<CSAFEResponseFrame, Concept2Error<T::Error>> {
+    transport.discard().map_err(Concept2Error::Transport)?;
+    transport
+        .send(&cmd.to_vec())
+        .map_err(Concept2Error::Transport)?;
+    match transport
+        .receive(timeout)
+        .map_err(Concept2Error::Transport)?
+    {
+        Some(v) => Ok(concept2response::parse_frame_at(&v, 0)?),
+        None => Err(Concept2Error::Timeout),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Answers every frame with the next canned response, and remembers what was sent.
+    struct Canned {
+        sent: Vec<Vec<u8>>,
+        responses: Vec<Vec<u8>>,
+    }
+
+    impl super::Transport for Canned {
+        type Error = std::convert::Infallible;
+
+        /// Canned responses are answers to frames that haven't been sent yet.
+        fn discard(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error> {
+            self.sent.push(frame.to_vec());
+            Ok(())
+        }
+
+        fn receive(
+            &mut self,
+            _timeout: std::time::Duration,
+        ) -> Result<Option<Vec<u8>>, Self::Error> {
+            Ok(if self.responses.is_empty() {
+                None
+            } else {
+                Some(self.responses.remove(0))
+            })
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut transport = Canned {
+            sent: Vec::new(),
+            responses: vec![vec![
+                0xf1, 0x81, 0x92, 0x5, 0x30, 0x30, 0x30, 0x30, 0x30, 0x26, 0xf2,
+            ]],
+        };
+        let cmd =
+            crate::csafe::CSAFEFrame::new(vec![crate::concept2command::Concept2Command::GetUserID]);
+        let frame = super::round_trip(&mut transport, &cmd, super::DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(vec![cmd.to_vec()], transport.sent);
+        assert_eq!(
+            vec![crate::concept2response::Concept2Response::GetUserID(
+                String::from("00000")
+            )],
+            frame.responses
+        );
+        assert!(matches!(
+            super::round_trip(&mut transport, &cmd, super::DEFAULT_TIMEOUT),
+            Err(crate::error::Concept2Error::Timeout)
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+//! CSAFE unit specifiers and the quantities that carry them.
+//!
+//! Any CSAFE value that can be measured in more than one way - distance, pace, power,
+//! weight - is followed on the wire by a units byte. Times are never sent with units;
+//! they're either hours/minutes/seconds or hundredths of a second, depending on the
+//! command, and are represented as `std::time::Duration`.
+
+pub use std::time::Duration;
+
+byte_enum! {
+    /// CSAFE unit specifiers.
+    pub enum Units {
+        Mile = 0x01,
+        TenthMile = 0x02,
+        HundredthMile = 0x03,
+        ThousandthMile = 0x04,
+        Feet = 0x05,
+        Inch = 0x06,
+        Pounds = 0x07,
+        TenthPounds = 0x08,
+        TenFeet = 0x0a,
+        MilesPerHour = 0x10,
+        TenthMilesPerHour = 0x11,
+        HundredthMilesPerHour = 0x12,
+        FeetPerMinute = 0x13,
+        Kilometer = 0x21,
+        TenthKilometer = 0x22,
+        HundredthKilometer = 0x23,
+        Meter = 0x24,
+        TenthMeter = 0x25,
+        Centimeter = 0x26,
+        Kilogram = 0x27,
+        TenthKilogram = 0x28,
+        KilometersPerHour = 0x30,
+        TenthKilometersPerHour = 0x31,
+        HundredthKilometersPerHour = 0x32,
+        MetersPerMinute = 0x33,
+        MinutesPerMile = 0x37,
+        MinutesPerKilometer = 0x38,
+        SecondsPerKilometer = 0x39,
+        SecondsPerMile = 0x3a,
+        Floors = 0x41,
+        TenthFloors = 0x42,
+        Steps = 0x43,
+        Revolutions = 0x44,
+        Strides = 0x45,
+        Strokes = 0x46,
+        Beats = 0x47,
+        Calories = 0x48,
+        Kp = 0x49,
+        PercentGrade = 0x4a,
+        HundredthPercentGrade = 0x4b,
+        TenthPercentGrade = 0x4c,
+        TenthFloorsPerMinute = 0x4f,
+        FloorsPerMinute = 0x50,
+        StepsPerMinute = 0x51,
+        RevolutionsPerMinute = 0x52,
+        StridesPerMinute = 0x53,
+        StrokesPerMinute = 0x54,
+        BeatsPerMinute = 0x55,
+        CaloriesPerMinute = 0x56,
+        CaloriesPerHour = 0x57,
+        Watts = 0x58,
+        Kpm = 0x59,
+        InchPounds = 0x5a,
+        FootPounds = 0x5b,
+        NewtonMeters = 0x5c,
+        Amperes = 0x61,
+        Milliamperes = 0x62,
+        Volts = 0x63,
+        Millivolts = 0x64,
+    }
+}
+
+const METERS_PER_MILE: f64 = 1609.344;
+const METERS_PER_FOOT: f64 = 0.3048;
+
+impl Units {
+    /// How many meters one of these units is, or `None` if this isn't a unit of distance.
+    pub fn meters_per_unit(self) -> Option<f64> {
+        match self {
+            Units::Mile => Some(METERS_PER_MILE),
+            Units::TenthMile => Some(METERS_PER_MILE / 10.0),
+            Units::HundredthMile => Some(METERS_PER_MILE / 100.0),
+            Units::ThousandthMile => Some(METERS_PER_MILE / 1000.0),
+            Units::Feet => Some(METERS_PER_FOOT),
+            Units::Inch => Some(METERS_PER_FOOT / 12.0),
+            Units::TenFeet => Some(METERS_PER_FOOT * 10.0),
+            Units::Kilometer => Some(1000.0),
+            Units::TenthKilometer => Some(100.0),
+            Units::HundredthKilometer => Some(10.0),
+            Units::Meter => Some(1.0),
+            Units::TenthMeter => Some(0.1),
+            Units::Centimeter => Some(0.01),
+            _ => None,
+        }
+    }
+
+    pub fn is_distance(self) -> bool {
+        self.meters_per_unit().is_some()
+    }
+}
+
+/// A distance as the machine reported it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Distance {
+    pub value: u32,
+    pub units: Units,
+}
+
+impl Distance {
+    pub fn new(value: u32, units: Units) -> Distance {
+        Distance { value, units }
+    }
+
+    pub fn from_meters(meters: u32) -> Distance {
+        Distance::new(meters, Units::Meter)
+    }
+
+    /// The distance in meters, or `None` if the units aren't a unit of distance.
+    pub fn meters(&self) -> Option<f64> {
+        self.units
+            .meters_per_unit()
+            .map(|m| f64::from(self.value) * m)
+    }
+}
+
+/// Any other value with a unit attached - pace, cadence, power, weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub value: u32,
+    pub units: Units,
+}
+
+impl Measurement {
+    pub fn new(value: u32, units: Units) -> Measurement {
+        Measurement { value, units }
+    }
+}
+
+/// The PM's proprietary commands measure time in hundredths of a second.
+pub fn from_hundredths(hundredths: u32) -> Duration {
+    Duration::from_millis(u64::from(hundredths) * 10)
+}
+
+/// Rounds down to the nearest hundredth, saturating at `u32::MAX`.
+pub fn to_hundredths(d: Duration) -> u32 {
+    let hundredths = d.as_millis() / 10;
+    if hundredths > u128::from(u32::MAX) {
+        u32::MAX
+    } else {
+        hundredths as u32
+    }
+}
+
+/// Public CSAFE commands send times as hours, minutes, and seconds.
+pub fn from_hms(hours: u8, minutes: u8, seconds: u8) -> Duration {
+    Duration::from_secs(u64::from(hours) * 3600 + u64::from(minutes) * 60 + u64::from(seconds))
+}
+
+/// The reverse of `from_hms`. Fractions of a second are dropped, and anything over
+/// 255 hours saturates.
+pub fn to_hms(d: Duration) -> (u8, u8, u8) {
+    let secs = d.as_secs();
+    if secs / 3600 > u64::from(u8::MAX) {
+        return (u8::MAX, 59, 59);
+    }
+    (
+        (secs / 3600) as u8,
+        (secs % 3600 / 60) as u8,
+        (secs % 60) as u8,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_distance_meters() {
+        assert_eq!(
+            Some(2172148.0),
+            super::Distance::new(2172148, super::Units::Meter).meters()
+        );
+        assert_eq!(
+            Some(2000.0),
+            super::Distance::new(2, super::Units::Kilometer).meters()
+        );
+        assert_eq!(None, super::Distance::new(10, super::Units::Watts).meters());
+    }
+
+    #[test]
+    fn test_hms_round_trip() {
+        let d = super::from_hms(1, 2, 3);
+        assert_eq!(3723, d.as_secs());
+        assert_eq!((1, 2, 3), super::to_hms(d));
+        assert_eq!(
+            (255, 59, 59),
+            super::to_hms(super::Duration::from_secs(1_000_000))
+        );
+    }
+
+    #[test]
+    fn test_hundredths() {
+        let d = super::from_hundredths(12345);
+        assert_eq!(123450, d.as_millis());
+        assert_eq!(12345, super::to_hundredths(d));
+    }
+}
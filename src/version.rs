@@ -0,0 +1,123 @@
+//! Decoding of the `GetVersion` response, which identifies the machine on the other
+//! end of the cable.
+//!
+//! The commands that a PM will accept depend on both its generation and its firmware, and
+//! the numbers that it reports mean different things on a SkiErg or BikeErg than on a
+//! rower, so this is usually the first thing to ask for.
+
+/// CSAFE manufacturer ID assigned to Concept2.
+pub const CONCEPT2_MANUFACTURER_ID: u8 = 22;
+
+byte_enum! {
+    /// Performance Monitor generations, from the `model` field.
+    pub enum PMModel {
+        PM3 = 3,
+        PM4 = 4,
+        PM5 = 5,
+    }
+}
+
+byte_enum! {
+    /// The machine that the PM is attached to, as reported by `GetErgMachineType`.
+    pub enum ErgMachineType {
+        StaticD = 0,
+        StaticC = 1,
+        StaticA = 2,
+        StaticB = 3,
+        StaticE = 5,
+        StaticSimulator = 7,
+        StaticDynamic = 8,
+        SlidesA = 16,
+        SlidesB = 17,
+        SlidesC = 18,
+        SlidesD = 19,
+        SlidesE = 20,
+        SlidesDynamic = 32,
+        StaticDyno = 64,
+        StaticSki = 128,
+        StaticSkiSimulator = 143,
+        Bike = 192,
+        BikeArms = 193,
+        BikeNoArms = 194,
+        BikeSimulator = 207,
+        MultiErgRow = 224,
+        MultiErgSki = 225,
+        MultiErgBike = 226,
+    }
+}
+
+/// The kind of erg, which decides what the PM's numbers mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErgType {
+    Rower,
+    SkiErg,
+    BikeErg,
+    /// A machine type that this library doesn't know about.
+    Unknown,
+}
+
+impl From<ErgMachineType> for ErgType {
+    fn from(machine: ErgMachineType) -> Self {
+        match machine {
+            ErgMachineType::StaticSki
+            | ErgMachineType::StaticSkiSimulator
+            | ErgMachineType::MultiErgSki => ErgType::SkiErg,
+            ErgMachineType::Bike
+            | ErgMachineType::BikeArms
+            | ErgMachineType::BikeNoArms
+            | ErgMachineType::BikeSimulator
+            | ErgMachineType::MultiErgBike => ErgType::BikeErg,
+            ErgMachineType::Unknown(_) => ErgType::Unknown,
+            _ => ErgType::Rower,
+        }
+    }
+}
+
+/// Everything reported by `GetVersion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub manufacturer_id: u8,
+    pub class_id: u8,
+    pub model: u8,
+    pub hardware_version: u16,
+    pub software_version: u16,
+}
+
+impl VersionInfo {
+    /// Whether the machine is a Concept2 at all.
+    pub fn is_concept2(&self) -> bool {
+        self.manufacturer_id == CONCEPT2_MANUFACTURER_ID
+    }
+
+    /// The kind of erg isn't part of the version, and has to be asked for separately
+    /// with `GetErgMachineType`.
+    pub fn pm_model(&self) -> PMModel {
+        PMModel::from(self.model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_classify() {
+        let version = super::VersionInfo {
+            manufacturer_id: 22,
+            class_id: 2,
+            model: 5,
+            hardware_version: 634,
+            software_version: 210,
+        };
+        assert!(version.is_concept2());
+        assert_eq!(super::PMModel::PM5, version.pm_model());
+        assert_eq!(super::PMModel::Unknown(9), super::PMModel::from(9));
+        assert_eq!(5, u8::from(super::PMModel::PM5));
+        assert_eq!(
+            super::ErgType::SkiErg,
+            super::ErgType::from(super::ErgMachineType::from(128))
+        );
+        assert_eq!(
+            super::ErgType::Rower,
+            super::ErgType::from(super::ErgMachineType::StaticD)
+        );
+    }
+}
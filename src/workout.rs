@@ -0,0 +1,161 @@
+//! Enumerations that the PM uses to describe workouts and what the rower is doing.
+//!
+//! Each of these is a single byte on the wire. Every enum has an `Unknown` variant so
+//! that a byte from newer firmware never fails to parse, and converting back to `u8`
+//! returns exactly the byte that was read.
+
+byte_enum! {
+    /// Where the PM is in the lifecycle of a workout.
+    pub enum WorkoutState {
+        WaitToBegin = 0,
+        WorkoutRow = 1,
+        CountdownPause = 2,
+        IntervalRest = 3,
+        IntervalWorkTime = 4,
+        IntervalWorkDistance = 5,
+        IntervalRestEndToWorkTime = 6,
+        IntervalRestEndToWorkDistance = 7,
+        IntervalWorkTimeToRest = 8,
+        IntervalWorkDistanceToRest = 9,
+        WorkoutEnd = 10,
+        Terminate = 11,
+        WorkoutLogged = 12,
+        Rearm = 13,
+    }
+}
+
+byte_enum! {
+    /// The kind of workout that is programmed, or being rowed.
+    pub enum WorkoutType {
+        JustRowNoSplits = 0,
+        JustRowSplits = 1,
+        FixedDistanceNoSplits = 2,
+        FixedDistanceSplits = 3,
+        FixedTimeNoSplits = 4,
+        FixedTimeSplits = 5,
+        FixedTimeInterval = 6,
+        FixedDistanceInterval = 7,
+        VariableInterval = 8,
+        VariableUndefinedRestInterval = 9,
+        FixedCalorie = 10,
+        FixedWattMinute = 11,
+        FixedCalorieInterval = 12,
+    }
+}
+
+byte_enum! {
+    /// What the current interval is measured by.
+    pub enum IntervalType {
+        Time = 0,
+        Distance = 1,
+        Rest = 2,
+        TimeRestUndefined = 3,
+        DistanceRestUndefined = 4,
+        RestUndefined = 5,
+        Calorie = 6,
+        CalorieRestUndefined = 7,
+        WattMinute = 8,
+        WattMinuteRestUndefined = 9,
+        None = 255,
+    }
+}
+
+byte_enum! {
+    /// The PM's overall operating mode.
+    pub enum OperationalState {
+        Reset = 0,
+        Ready = 1,
+        Workout = 2,
+        Warmup = 3,
+        Race = 4,
+        PowerOff = 5,
+        Pause = 6,
+        InvokeBootloader = 7,
+        PowerOffShip = 8,
+        IdleCharge = 9,
+        Idle = 10,
+        ManufacturingTest = 11,
+        FirmwareUpdate = 12,
+        DragFactor = 13,
+        DragFactorCalibration = 100,
+    }
+}
+
+byte_enum! {
+    /// Whether the flywheel is being worked.
+    pub enum RowingState {
+        Inactive = 0,
+        Active = 1,
+    }
+}
+
+byte_enum! {
+    /// Where the rower is inside of a single stroke.
+    pub enum StrokeState {
+        WaitingForWheelToReachMinSpeed = 0,
+        WaitingForWheelToAccelerate = 1,
+        Driving = 2,
+        DwellingAfterDrive = 3,
+        Recovery = 4,
+    }
+}
+
+byte_enum! {
+    /// What the number in a workout or split duration is measured in.
+    pub enum DurationType {
+        /// Hundredths of a second.
+        Time = 0x00,
+        Calories = 0x40,
+        WattMinutes = 0x60,
+        /// Meters.
+        Distance = 0x80,
+    }
+}
+
+byte_enum! {
+    /// Which set of screens `SetScreenState` is addressing.
+    pub enum ScreenType {
+        None = 0,
+        Workout = 1,
+        Race = 2,
+        Csafe = 3,
+        Diagnostic = 4,
+        Manufacturing = 5,
+    }
+}
+
+byte_enum! {
+    /// Screen values for `ScreenType::Workout`.
+    pub enum WorkoutScreen {
+        None = 0,
+        /// Starts the programmed workout - the "go to in-use" screen.
+        PrepareToRowWorkout = 1,
+        TerminateWorkout = 2,
+        RearmWorkout = 3,
+        RefreshLogCard = 4,
+        PrepareToRaceStart = 5,
+        GoToMainScreen = 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_byte_enum_round_trip() {
+        use crate::workout::IntervalType;
+        assert_eq!(IntervalType::Distance, IntervalType::from(1));
+        assert_eq!(IntervalType::None, IntervalType::from(255));
+        assert_eq!(IntervalType::Unknown(42), IntervalType::from(42));
+        for b in 0..=255u8 {
+            assert_eq!(b, u8::from(IntervalType::from(b)));
+        }
+    }
+
+    #[test]
+    fn test_workout_type() {
+        use crate::workout::WorkoutType;
+        assert_eq!(WorkoutType::VariableInterval, WorkoutType::from(8));
+        assert_eq!(12, u8::from(WorkoutType::FixedCalorieInterval));
+        assert_eq!(WorkoutType::Unknown(13), WorkoutType::from(13));
+    }
+}
@@ -0,0 +1,449 @@
+/// Describing a workout, and turning it into the frames that program it onto the PM.
+///
+/// A `WorkoutBuilder` starts from the shape of the session - just row, a single piece of
+/// distance or time, or a set of intervals with rest between them - and takes a split
+/// and a target pace on top. `build` checks the session against what the PM accepts, and
+/// returns the frames to send, in order:
+///
+/// `for frame in WorkoutBuilder::intervals(8, Goal::Distance(500), rest).build()? {`
+/// `    pm.execute(frame.commands().to_vec())?;`
+/// `}`
+///
+/// A single piece goes out in one frame. Intervals are programmed as a variable interval
+/// workout, one frame per interval, since that's the only kind that the PM stops after a
+/// set number of intervals. The last frame always moves the PM to the screen that's ready
+/// to row the workout.
+use std::fmt;
+
+use crate::concept2command::{Concept2Command, Concept2ProprietaryConfigCommand};
+use crate::csafe::CSAFEFrame;
+use crate::units::{self, Duration};
+use crate::workout::{DurationType, IntervalType, ScreenType, WorkoutScreen, WorkoutType};
+
+/// The shortest and longest distances, in meters, of a piece or an interval.
+pub const MIN_DISTANCE: u32 = 100;
+pub const MAX_DISTANCE: u32 = 50_000;
+/// The shortest and longest times of a piece or an interval.
+pub const MIN_TIME: Duration = Duration::from_secs(20);
+pub const MAX_TIME: Duration = Duration::from_secs(9 * 3600 + 59 * 60 + 59);
+/// The most intervals in a variable interval workout.
+pub const MAX_INTERVALS: u8 = 30;
+/// The longest rest between intervals.
+pub const MAX_REST: Duration = Duration::from_secs(9 * 60 + 55);
+/// The most splits that a piece can be divided into.
+pub const MAX_SPLITS: u32 = 30;
+/// The slowest target pace, per 500 meters.
+pub const MAX_PACE: Duration = Duration::from_secs(10 * 60);
+
+/// How long a piece, an interval, or a split lasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Meters.
+    Distance(u32),
+    Time(Duration),
+}
+
+impl Goal {
+    /// As it's sent in `SetWorkoutDuration` and `SetSplitDuration`.
+    fn duration(self) -> (DurationType, u32) {
+        match self {
+            Goal::Distance(meters) => (DurationType::Distance, meters),
+            Goal::Time(time) => (DurationType::Time, units::to_hundredths(time)),
+        }
+    }
+
+    fn check(self) -> Result<(), WorkoutError> {
+        match self {
+            Goal::Distance(meters) if !(MIN_DISTANCE..=MAX_DISTANCE).contains(&meters) => {
+                Err(WorkoutError::DistanceOutOfRange { meters })
+            }
+            Goal::Time(time) if !(MIN_TIME..=MAX_TIME).contains(&time) => {
+                Err(WorkoutError::TimeOutOfRange { time })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A session that the PM won't take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkoutError {
+    /// A piece, interval, or split is shorter than `MIN_DISTANCE` or longer than
+    /// `MAX_DISTANCE`.
+    DistanceOutOfRange {
+        meters: u32,
+    },
+    /// A piece, interval, or split is shorter than `MIN_TIME` or longer than `MAX_TIME`.
+    TimeOutOfRange {
+        time: Duration,
+    },
+    /// Splits must be measured the same way as the piece that they divide.
+    SplitMismatch,
+    /// The split divides the piece into more than `MAX_SPLITS` parts.
+    TooManySplits {
+        splits: u32,
+    },
+    /// Intervals can't be split.
+    SplitIntervals,
+    /// There must be between 1 and `MAX_INTERVALS` intervals.
+    IntervalCountOutOfRange {
+        count: u8,
+    },
+    RestOutOfRange {
+        rest: Duration,
+    },
+    /// The PM only takes rest in whole seconds.
+    FractionalRest {
+        rest: Duration,
+    },
+    TargetPaceOutOfRange {
+        pace: Duration,
+    },
+}
+
+impl fmt::Display for WorkoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkoutError::DistanceOutOfRange { meters } => write!(
+                f,
+                "distance of {} m is outside of {} to {} m",
+                meters, MIN_DISTANCE, MAX_DISTANCE
+            ),
+            WorkoutError::TimeOutOfRange { time } => write!(
+                f,
+                "time of {:?} is outside of {:?} to {:?}",
+                time, MIN_TIME, MAX_TIME
+            ),
+            WorkoutError::SplitMismatch => {
+                write!(f, "split must be measured the same way as the piece")
+            }
+            WorkoutError::TooManySplits { splits } => write!(
+                f,
+                "split divides the piece into {} parts, but the PM allows {}",
+                splits, MAX_SPLITS
+            ),
+            WorkoutError::SplitIntervals => write!(f, "intervals can't be split"),
+            WorkoutError::IntervalCountOutOfRange { count } => write!(
+                f,
+                "{} intervals is outside of 1 to {}",
+                count, MAX_INTERVALS
+            ),
+            WorkoutError::RestOutOfRange { rest } => {
+                write!(f, "rest of {:?} is longer than {:?}", rest, MAX_REST)
+            }
+            WorkoutError::FractionalRest { rest } => {
+                write!(f, "rest of {:?} isn't a whole number of seconds", rest)
+            }
+            WorkoutError::TargetPaceOutOfRange { pace } => write!(
+                f,
+                "target pace of {:?} is outside of 0 to {:?}",
+                pace, MAX_PACE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WorkoutError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Session {
+    JustRow,
+    Piece(Goal),
+    Intervals {
+        count: u8,
+        work: Goal,
+        rest: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkoutBuilder {
+    session: Session,
+    split: Option<Goal>,
+    target_pace: Option<Duration>,
+}
+
+impl WorkoutBuilder {
+    /// Rowing with no end.
+    pub fn just_row() -> WorkoutBuilder {
+        WorkoutBuilder::new(Session::JustRow)
+    }
+
+    /// A single piece of this many meters.
+    pub fn distance(meters: u32) -> WorkoutBuilder {
+        WorkoutBuilder::new(Session::Piece(Goal::Distance(meters)))
+    }
+
+    /// A single piece of this long.
+    pub fn time(time: Duration) -> WorkoutBuilder {
+        WorkoutBuilder::new(Session::Piece(Goal::Time(time)))
+    }
+
+    /// `count` intervals of `work`, with `rest` after each one. Rest must be a whole
+    /// number of seconds.
+    pub fn intervals(count: u8, work: Goal, rest: Duration) -> WorkoutBuilder {
+        WorkoutBuilder::new(Session::Intervals { count, work, rest })
+    }
+
+    fn new(session: Session) -> WorkoutBuilder {
+        WorkoutBuilder {
+            session,
+            split: None,
+            target_pace: None,
+        }
+    }
+
+    /// Divides a piece, or just rowing, into splits.
+    pub fn split(mut self, split: Goal) -> WorkoutBuilder {
+        self.split = Some(split);
+        self
+    }
+
+    /// The pace to hold, per 500 meters.
+    pub fn target_pace(mut self, pace: Duration) -> WorkoutBuilder {
+        self.target_pace = Some(pace);
+        self
+    }
+
+    /// Checks the session against what the PM accepts.
+    pub fn validate(&self) -> Result<(), WorkoutError> {
+        match self.session {
+            Session::JustRow => (),
+            Session::Piece(goal) => goal.check()?,
+            Session::Intervals { count, work, rest } => {
+                if count == 0 || count > MAX_INTERVALS {
+                    return Err(WorkoutError::IntervalCountOutOfRange { count });
+                }
+                work.check()?;
+                if rest > MAX_REST {
+                    return Err(WorkoutError::RestOutOfRange { rest });
+                }
+                if rest.subsec_nanos() != 0 {
+                    return Err(WorkoutError::FractionalRest { rest });
+                }
+            }
+        }
+        if let Some(split) = self.split {
+            split.check()?;
+            match self.session {
+                Session::JustRow => (),
+                Session::Piece(goal) => {
+                    let splits = match (goal, split) {
+                        (Goal::Distance(total), Goal::Distance(split)) => total.div_ceil(split),
+                        (Goal::Time(total), Goal::Time(split)) => {
+                            units::to_hundredths(total).div_ceil(units::to_hundredths(split))
+                        }
+                        _ => return Err(WorkoutError::SplitMismatch),
+                    };
+                    if splits > MAX_SPLITS {
+                        return Err(WorkoutError::TooManySplits { splits });
+                    }
+                }
+                Session::Intervals { .. } => return Err(WorkoutError::SplitIntervals),
+            }
+        }
+        match self.target_pace {
+            Some(pace) if pace == Duration::from_secs(0) || pace > MAX_PACE => {
+                Err(WorkoutError::TargetPaceOutOfRange { pace })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The frames that program this workout, in the order that they must be sent.
+    pub fn build(&self) -> Result<Vec<CSAFEFrame>, WorkoutError> {
+        self.validate()?;
+        let mut frames = vec![];
+        match self.session {
+            Session::JustRow | Session::Piece(_) => {
+                let workout_type = match (self.session, self.split) {
+                    (Session::Piece(Goal::Distance(_)), Some(_)) => {
+                        WorkoutType::FixedDistanceSplits
+                    }
+                    (Session::Piece(Goal::Distance(_)), None) => WorkoutType::FixedDistanceNoSplits,
+                    (Session::Piece(Goal::Time(_)), Some(_)) => WorkoutType::FixedTimeSplits,
+                    (Session::Piece(Goal::Time(_)), None) => WorkoutType::FixedTimeNoSplits,
+                    (_, Some(_)) => WorkoutType::JustRowSplits,
+                    (_, None) => WorkoutType::JustRowNoSplits,
+                };
+                let mut commands = vec![Concept2ProprietaryConfigCommand::SetWorkoutType(
+                    workout_type,
+                )];
+                if let Session::Piece(goal) = self.session {
+                    let (duration_type, value) = goal.duration();
+                    commands.push(Concept2ProprietaryConfigCommand::SetWorkoutDuration(
+                        duration_type,
+                        value,
+                    ));
+                }
+                if let Some(split) = self.split {
+                    let (duration_type, value) = split.duration();
+                    commands.push(Concept2ProprietaryConfigCommand::SetSplitDuration(
+                        duration_type,
+                        value,
+                    ));
+                }
+                commands.extend(self.target_pace_command());
+                commands.push(Concept2ProprietaryConfigCommand::ConfigureWorkout(true));
+                frames.push(config_frame(commands));
+            }
+            Session::Intervals { count, work, rest } => {
+                frames.push(config_frame(vec![
+                    Concept2ProprietaryConfigCommand::SetWorkoutType(WorkoutType::VariableInterval),
+                ]));
+                let interval_type = match work {
+                    Goal::Distance(_) => IntervalType::Distance,
+                    Goal::Time(_) => IntervalType::Time,
+                };
+                let (duration_type, value) = work.duration();
+                for i in 0..count {
+                    let mut commands = vec![
+                        Concept2ProprietaryConfigCommand::SetWorkoutIntervalCount(i),
+                        Concept2ProprietaryConfigCommand::SetIntervalType(interval_type),
+                        Concept2ProprietaryConfigCommand::SetWorkoutDuration(duration_type, value),
+                        Concept2ProprietaryConfigCommand::SetRestDuration(rest),
+                    ];
+                    commands.extend(self.target_pace_command());
+                    commands.push(Concept2ProprietaryConfigCommand::ConfigureWorkout(true));
+                    frames.push(config_frame(commands));
+                }
+            }
+        }
+        frames.push(config_frame(vec![
+            Concept2ProprietaryConfigCommand::SetScreenState(
+                ScreenType::Workout,
+                u8::from(WorkoutScreen::PrepareToRowWorkout),
+            ),
+        ]));
+        Ok(frames)
+    }
+
+    fn target_pace_command(&self) -> Option<Concept2ProprietaryConfigCommand> {
+        self.target_pace
+            .map(Concept2ProprietaryConfigCommand::SetTargetPaceTime)
+    }
+}
+
+fn config_frame(commands: Vec<Concept2ProprietaryConfigCommand>) -> CSAFEFrame {
+    CSAFEFrame::new(vec![Concept2Command::ProprietaryConfigCommand(commands)])
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_validate() {
+        use super::{Goal, WorkoutBuilder, WorkoutError};
+        use std::time::Duration;
+        let rest = Duration::from_secs(90);
+        assert_eq!(Ok(()), WorkoutBuilder::distance(2000).validate());
+        assert_eq!(
+            Err(WorkoutError::DistanceOutOfRange { meters: 50 }),
+            WorkoutBuilder::distance(50).validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::TimeOutOfRange {
+                time: Duration::from_secs(36_000)
+            }),
+            WorkoutBuilder::time(Duration::from_secs(36_000)).validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::SplitMismatch),
+            WorkoutBuilder::distance(2000)
+                .split(Goal::Time(Duration::from_secs(60)))
+                .validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::TooManySplits { splits: 31 }),
+            WorkoutBuilder::distance(3050)
+                .split(Goal::Distance(100))
+                .validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::IntervalCountOutOfRange { count: 0 }),
+            WorkoutBuilder::intervals(0, Goal::Distance(500), rest).validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::RestOutOfRange {
+                rest: Duration::from_secs(600)
+            }),
+            WorkoutBuilder::intervals(4, Goal::Distance(500), Duration::from_secs(600)).validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::FractionalRest {
+                rest: Duration::from_millis(90_500)
+            }),
+            WorkoutBuilder::intervals(4, Goal::Distance(500), Duration::from_millis(90_500))
+                .validate()
+        );
+        assert_eq!(
+            Err(WorkoutError::SplitIntervals),
+            WorkoutBuilder::intervals(4, Goal::Distance(500), rest)
+                .split(Goal::Distance(100))
+                .validate()
+        );
+    }
+
+    #[test]
+    fn test_build_piece() {
+        use crate::concept2command::{Concept2Command, Concept2ProprietaryConfigCommand};
+        use crate::workout::{DurationType, ScreenType, WorkoutType};
+        let frames = super::WorkoutBuilder::distance(2000)
+            .split(super::Goal::Distance(500))
+            .build()
+            .unwrap();
+        assert_eq!(2, frames.len());
+        assert_eq!(
+            &[Concept2Command::ProprietaryConfigCommand(vec![
+                Concept2ProprietaryConfigCommand::SetWorkoutType(WorkoutType::FixedDistanceSplits),
+                Concept2ProprietaryConfigCommand::SetWorkoutDuration(DurationType::Distance, 2000),
+                Concept2ProprietaryConfigCommand::SetSplitDuration(DurationType::Distance, 500),
+                Concept2ProprietaryConfigCommand::ConfigureWorkout(true),
+            ])],
+            frames[0].commands()
+        );
+        assert_eq!(
+            &[Concept2Command::ProprietaryConfigCommand(vec![
+                Concept2ProprietaryConfigCommand::SetScreenState(ScreenType::Workout, 1),
+            ])],
+            frames[1].commands()
+        );
+    }
+
+    #[test]
+    fn test_program_intervals() {
+        use crate::units::Duration;
+        use crate::workout::{WorkoutState, WorkoutType};
+        // 8 x 500m / 1:30 rest, at 1:45.
+        let frames = super::WorkoutBuilder::intervals(
+            8,
+            super::Goal::Distance(500),
+            Duration::from_secs(90),
+        )
+        .target_pace(Duration::from_secs(105))
+        .build()
+        .unwrap();
+        assert_eq!(10, frames.len());
+
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_pace(Duration::from_secs(105));
+        let mut pm = crate::monitor::PerformanceMonitor::new(simulator);
+        for frame in frames {
+            pm.execute(frame.commands().to_vec()).unwrap();
+        }
+        assert_eq!(
+            Ok((
+                WorkoutType::VariableInterval,
+                WorkoutState::WaitToBegin,
+                Duration::from_secs(105)
+            )),
+            pm.execute((
+                crate::command::GetWorkoutType,
+                crate::command::GetWorkoutState,
+                crate::command::GetTargetPaceTime,
+            ))
+        );
+        pm.transport_mut().start_rowing();
+        pm.transport_mut().advance(Duration::from_secs(106));
+        assert_eq!(Ok(WorkoutState::IntervalRest), pm.workout_state());
+    }
+}
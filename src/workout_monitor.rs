@@ -0,0 +1,447 @@
+/// Sampling a workout while it's being rowed.
+///
+/// A `WorkoutMonitor` queries the PM on a `Schedule`: stroke data (pace, power, and
+/// stroke rate) often, and the workout's totals (time, distance, calories, and heart
+/// rate, with the belt that it comes from) less often. Whatever is due goes out in a
+/// single frame. Each successful poll queues a `WorkoutSample` with the most recent
+/// value of everything.
+///
+/// The queue holds a fixed number of samples. A consumer that falls behind loses the
+/// oldest ones rather than the newest, and `dropped` says how many it missed. A value
+/// that should have been refreshed but wasn't, because the poll that was meant to
+/// refresh it failed, makes the samples `stale` until it's read again.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::concept2command::{Concept2Command, Concept2ProprietaryCommand};
+use crate::concept2response::{Concept2Response, Concept2ResponseProprietary, HeartRateBelt};
+use crate::error::Concept2Error;
+use crate::heart_rate::HeartRate;
+use crate::monitor::PerformanceMonitor;
+use crate::transport::Transport;
+use crate::units::Distance;
+
+/// How many samples are kept for a consumer unless told otherwise.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// How often each group of values is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    /// Pace, power, and stroke rate.
+    pub stroke: Duration,
+    /// Time, distance, calories, and heart rate.
+    pub totals: Duration,
+}
+
+/// Stroke data at 10 Hz, and totals at 1 Hz.
+impl Default for Schedule {
+    fn default() -> Schedule {
+        Schedule {
+            stroke: Duration::from_millis(100),
+            totals: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Everything known about the workout at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkoutSample {
+    /// When the poll that produced this sample started, before its request was sent.
+    pub timestamp: Instant,
+    /// Time into the workout, or the current interval.
+    pub elapsed: Duration,
+    /// Distance into the workout, or the current interval.
+    pub distance: Distance,
+    /// Time per 500 meters, for the most recent stroke.
+    pub pace: Duration,
+    /// Watts, for the most recent stroke.
+    pub power: u32,
+    /// Strokes per minute.
+    pub stroke_rate: u8,
+    pub heart_rate: HeartRate,
+    pub calories: u16,
+    /// Whether any of these values missed a refresh.
+    pub stale: bool,
+}
+
+/// The latest value of everything, as it's read.
+#[derive(Debug, Clone, Copy)]
+struct Readings {
+    elapsed: Duration,
+    distance: Distance,
+    pace: Duration,
+    power: u32,
+    stroke_rate: u8,
+    heart_rate: u8,
+    heart_rate_belt: HeartRateBelt,
+    calories: u16,
+}
+
+impl Default for Readings {
+    fn default() -> Readings {
+        Readings {
+            elapsed: Duration::from_secs(0),
+            distance: Distance::from_meters(0),
+            pace: Duration::from_secs(0),
+            power: 0,
+            stroke_rate: 0,
+            heart_rate: 0,
+            heart_rate_belt: HeartRateBelt::default(),
+            calories: 0,
+        }
+    }
+}
+
+/// When a group of values is next due, and whether the last attempt to read it worked.
+#[derive(Debug, Clone, Copy)]
+struct Group {
+    interval: Duration,
+    /// `None` until the first poll, which reads everything.
+    due: Option<Instant>,
+    fresh: bool,
+}
+
+impl Group {
+    fn new(interval: Duration) -> Group {
+        Group {
+            interval,
+            due: None,
+            fresh: false,
+        }
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        match self.due {
+            Some(due) => due <= now,
+            None => true,
+        }
+    }
+
+    /// Schedules the next read. A poll that's running late doesn't cause a burst of reads
+    /// to catch up; the schedule starts over from `now` instead.
+    fn reschedule(&mut self, now: Instant) {
+        let next = self.due.map_or(now, |due| due + self.interval);
+        self.due = Some(if next > now {
+            next
+        } else {
+            now + self.interval
+        });
+    }
+}
+
+pub struct WorkoutMonitor<T: Transport> {
+    pm: PerformanceMonitor<T>,
+    stroke: Group,
+    totals: Group,
+    readings: Readings,
+    samples: VecDeque<WorkoutSample>,
+    capacity: usize,
+    dropped: usize,
+}
+
+impl<T: Transport> WorkoutMonitor<T> {
+    pub fn new(pm: PerformanceMonitor<T>) -> WorkoutMonitor<T> {
+        let schedule = Schedule::default();
+        WorkoutMonitor {
+            pm,
+            stroke: Group::new(schedule.stroke),
+            totals: Group::new(schedule.totals),
+            readings: Readings::default(),
+            samples: VecDeque::new(),
+            capacity: DEFAULT_CAPACITY,
+            dropped: 0,
+        }
+    }
+
+    /// Takes effect after each group's next read.
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.stroke.interval = schedule.stroke;
+        self.totals.interval = schedule.totals;
+    }
+
+    /// How many samples to hold before the oldest are thrown away. At least one is
+    /// always kept.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = std::cmp::max(capacity, 1);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub fn monitor(&self) -> &PerformanceMonitor<T> {
+        &self.pm
+    }
+
+    pub fn monitor_mut(&mut self) -> &mut PerformanceMonitor<T> {
+        &mut self.pm
+    }
+
+    pub fn into_inner(self) -> PerformanceMonitor<T> {
+        self.pm
+    }
+
+    /// When the next poll is due, or `None` if one is due right away.
+    pub fn next_poll(&self) -> Option<Instant> {
+        match (self.stroke.due, self.totals.due) {
+            (Some(stroke), Some(totals)) => Some(std::cmp::min(stroke, totals)),
+            _ => None,
+        }
+    }
+
+    /// Reads whatever is due at `now`, and queues a sample if anything was read. Returns
+    /// whether a sample was queued. If the read fails, the values that it was meant to
+    /// refresh are marked stale, and are tried again at their next scheduled time.
+    pub fn poll_at(&mut self, now: Instant) -> Result<bool, Concept2Error<T::Error>> {
+        let stroke = self.stroke.is_due(now);
+        let totals = self.totals.is_due(now);
+        if !stroke && !totals {
+            return Ok(false);
+        }
+        let mut proprietary = Vec::new();
+        let mut commands = Vec::new();
+        if stroke {
+            self.stroke.reschedule(now);
+            proprietary.extend(vec![
+                Concept2ProprietaryCommand::GetStrokePace,
+                Concept2ProprietaryCommand::GetStrokePower,
+                Concept2ProprietaryCommand::GetStrokeRate,
+            ]);
+        }
+        if totals {
+            self.totals.reschedule(now);
+            proprietary.extend(vec![
+                Concept2ProprietaryCommand::GetWorkTime,
+                Concept2ProprietaryCommand::GetWorkDistance,
+                Concept2ProprietaryCommand::GetHeartRateBeltInfo,
+            ]);
+            commands.extend(vec![
+                Concept2Command::GetCalories,
+                Concept2Command::GetHeartRate,
+            ]);
+        }
+        commands.insert(0, Concept2Command::ProprietaryCommand(proprietary));
+
+        let frame = match self.pm.execute(commands) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.stroke.fresh &= !stroke;
+                self.totals.fresh &= !totals;
+                return Err(e);
+            }
+        };
+        self.stroke.fresh |= stroke;
+        self.totals.fresh |= totals;
+        for response in frame.responses {
+            self.record(response);
+        }
+        self.push(now);
+        Ok(true)
+    }
+
+    /// `poll_at`, right now.
+    pub fn poll(&mut self) -> Result<bool, Concept2Error<T::Error>> {
+        self.poll_at(Instant::now())
+    }
+
+    /// Sleeps until a poll is due, and polls until there's a sample. Samples that are
+    /// already queued are returned first.
+    pub fn wait(&mut self) -> Result<WorkoutSample, Concept2Error<T::Error>> {
+        loop {
+            if let Some(sample) = self.samples.pop_front() {
+                return Ok(sample);
+            }
+            if let Some(due) = self.next_poll() {
+                std::thread::sleep(due.saturating_duration_since(Instant::now()));
+            }
+            self.poll()?;
+        }
+    }
+
+    /// An endless stream of samples, from `wait`.
+    pub fn samples(
+        &mut self,
+    ) -> impl Iterator<Item = Result<WorkoutSample, Concept2Error<T::Error>>> + '_ {
+        std::iter::from_fn(move || Some(self.wait()))
+    }
+
+    /// The oldest queued sample.
+    pub fn next_sample(&mut self) -> Option<WorkoutSample> {
+        self.samples.pop_front()
+    }
+
+    /// The number of queued samples.
+    pub fn pending(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The number of samples that were thrown away because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn record(&mut self, response: Concept2Response) {
+        let readings = &mut self.readings;
+        match response {
+            Concept2Response::GetCalories(calories) => readings.calories = calories,
+            Concept2Response::GetHeartRate(heart_rate) => readings.heart_rate = heart_rate,
+            Concept2Response::ProprietaryCommand(responses) => {
+                for response in responses {
+                    match response {
+                        Concept2ResponseProprietary::GetStrokePace(pace) => readings.pace = pace,
+                        Concept2ResponseProprietary::GetStrokePower(power) => {
+                            readings.power = power
+                        }
+                        Concept2ResponseProprietary::GetStrokeRate(rate) => {
+                            readings.stroke_rate = rate
+                        }
+                        Concept2ResponseProprietary::GetWorkTime(time) => readings.elapsed = time,
+                        Concept2ResponseProprietary::GetWorkDistance(distance) => {
+                            readings.distance = distance
+                        }
+                        Concept2ResponseProprietary::GetHeartRateBeltInfo(belt) => {
+                            readings.heart_rate_belt = belt
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn push(&mut self, timestamp: Instant) {
+        let r = self.readings;
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+            self.dropped += 1;
+        }
+        self.samples.push_back(WorkoutSample {
+            timestamp,
+            elapsed: r.elapsed,
+            distance: r.distance,
+            pace: r.pace,
+            power: r.power,
+            stroke_rate: r.stroke_rate,
+            heart_rate: HeartRate::new(r.heart_rate, r.heart_rate_belt),
+            calories: r.calories,
+            stale: !(self.stroke.fresh && self.totals.fresh),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// A simulator on a cable that can be pulled.
+    struct Flaky {
+        simulator: crate::simulator::Simulator,
+        fail: bool,
+    }
+
+    impl crate::transport::Transport for Flaky {
+        type Error = ();
+
+        fn discard(&mut self) -> Result<(), ()> {
+            crate::transport::Transport::discard(&mut self.simulator).map_err(|e| match e {})
+        }
+
+        fn send(&mut self, frame: &[u8]) -> Result<(), ()> {
+            if self.fail {
+                return Err(());
+            }
+            crate::transport::Transport::send(&mut self.simulator, frame).map_err(|e| match e {})
+        }
+
+        fn receive(&mut self, timeout: std::time::Duration) -> Result<Option<Vec<u8>>, ()> {
+            crate::transport::Transport::receive(&mut self.simulator, timeout)
+                .map_err(|e| match e {})
+        }
+    }
+
+    fn rowing() -> super::WorkoutMonitor<Flaky> {
+        let mut simulator = crate::simulator::Simulator::new();
+        simulator.set_stroke_rate(24);
+        simulator.set_heart_rate(150);
+        simulator.set_heart_rate_belt(crate::concept2response::HeartRateBelt {
+            manufacturer_id: 1,
+            device_type: 120,
+            belt_id: 12345,
+        });
+        simulator.start_rowing();
+        super::WorkoutMonitor::new(crate::monitor::PerformanceMonitor::new(Flaky {
+            simulator,
+            fail: false,
+        }))
+    }
+
+    #[test]
+    fn test_schedule() {
+        use std::time::Duration;
+        let mut wm = rowing();
+        let start = std::time::Instant::now();
+        assert_eq!(Ok(true), wm.poll_at(start));
+        let first = wm.next_sample().unwrap();
+        assert_eq!(24, first.stroke_rate);
+        assert_eq!(Some(3), first.heart_rate.zone(190));
+        assert!(!first.stale);
+        assert_eq!(Some(start + Duration::from_millis(100)), wm.next_poll());
+
+        // Nothing is due yet.
+        assert_eq!(Ok(false), wm.poll_at(start + Duration::from_millis(50)));
+
+        // Stroke data is due, but the totals aren't.
+        wm.monitor_mut()
+            .transport_mut()
+            .simulator
+            .advance(Duration::from_millis(100));
+        assert_eq!(Ok(true), wm.poll_at(start + Duration::from_millis(100)));
+        assert_eq!(first.elapsed, wm.next_sample().unwrap().elapsed);
+
+        wm.monitor_mut()
+            .transport_mut()
+            .simulator
+            .advance(Duration::from_millis(900));
+        assert_eq!(Ok(true), wm.poll_at(start + Duration::from_secs(1)));
+        assert_eq!(Duration::from_secs(1), wm.next_sample().unwrap().elapsed);
+    }
+
+    #[test]
+    fn test_backpressure() {
+        use std::time::Duration;
+        let mut wm = rowing();
+        wm.set_capacity(2);
+        let start = std::time::Instant::now();
+        for i in 0..3 {
+            assert_eq!(Ok(true), wm.poll_at(start + Duration::from_millis(i * 100)));
+        }
+        assert_eq!(2, wm.pending());
+        assert_eq!(1, wm.dropped());
+        assert_eq!(
+            start + Duration::from_millis(100),
+            wm.next_sample().unwrap().timestamp
+        );
+    }
+
+    #[test]
+    fn test_stale() {
+        use std::time::Duration;
+        let mut wm = rowing();
+        let start = std::time::Instant::now();
+        wm.poll_at(start).unwrap();
+
+        // The totals can't be read when they're due...
+        wm.monitor_mut().transport_mut().fail = true;
+        assert!(wm.poll_at(start + Duration::from_secs(1)).is_err());
+        wm.monitor_mut().transport_mut().fail = false;
+
+        // ...so they're stale until the next time they are.
+        wm.poll_at(start + Duration::from_millis(1100)).unwrap();
+        wm.poll_at(start + Duration::from_secs(2)).unwrap();
+        assert_eq!(
+            vec![false, true, false],
+            std::iter::from_fn(|| wm.next_sample())
+                .map(|s| s.stale)
+                .collect::<Vec<bool>>()
+        );
+    }
+}